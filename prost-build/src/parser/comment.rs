@@ -0,0 +1,278 @@
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_till, take_until, take_while};
+use nom::character::complete::char;
+use nom::combinator::{map, opt};
+use nom::sequence::{delimited, preceded, terminated};
+
+use super::{IResult, Span};
+
+/// The comments attached to a declaration.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(super) struct Comments {
+    pub(super) leading: Option<String>,
+    pub(super) trailing: Option<String>,
+    pub(super) detached: Vec<String>,
+}
+
+/// The syntactic form of a comment.
+///
+/// Some tools treat `///` and `/** */` comments as documentation, and other comments as ordinary
+/// remarks. The style doesn't affect how comments are attached to declarations, and isn't exposed
+/// by the public API yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum CommentStyle {
+    /// A `//` comment.
+    Line,
+    /// A `///` comment.
+    DocLine,
+    /// A `/* */` comment.
+    Block,
+    /// A `/** */` comment.
+    DocBlock,
+}
+
+/// Parses a single `//` or `/* */` comment, returning its style and text.
+pub(super) fn parse(input: Span) -> IResult<(CommentStyle, String)> {
+    alt((
+        map(line, |text| {
+            // As in Rust, `////` is an ordinary comment.
+            let style = if text.starts_with('/') && !text.starts_with("//") {
+                CommentStyle::DocLine
+            } else {
+                CommentStyle::Line
+            };
+            (style, text)
+        }),
+        map(block, |text| {
+            // `/**/` and `/*** */` are ordinary comments.
+            let style = if text.starts_with('*') && text != "*" && !text.starts_with("**") {
+                CommentStyle::DocBlock
+            } else {
+                CommentStyle::Block
+            };
+            (style, text)
+        }),
+    ))(input)
+}
+
+/// Parses a `//` comment up to the end of the line, returning its text without the newline.
+fn line(input: Span) -> IResult<String> {
+    map(
+        preceded(tag("//"), take_till(|c| c == '\n')),
+        |text: Span| text.fragment().to_string(),
+    )(input)
+}
+
+/// Parses a `/* */` comment.
+///
+/// As with `protoc`, leading whitespace and a leading `*` are stripped from every line after the
+/// first.
+fn block(input: Span) -> IResult<String> {
+    map(
+        delimited(tag("/*"), take_until("*/"), tag("*/")),
+        |text: Span| {
+            let mut lines = text.fragment().split('\n');
+            let mut content = lines.next().unwrap_or_default().to_string();
+            for line in lines {
+                let line = line.trim_start_matches(is_horizontal_whitespace);
+                content.push('\n');
+                content.push_str(line.strip_prefix('*').unwrap_or(line));
+            }
+            content
+        },
+    )(input)
+}
+
+fn is_horizontal_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\r' | '\x0b' | '\x0c')
+}
+
+fn horizontal_whitespace(input: Span) -> IResult<Span> {
+    take_while(is_horizontal_whitespace)(input)
+}
+
+/// Accumulates comments into blocks, mirroring `protoc`'s tokenizer.
+struct Collector {
+    comments: Comments,
+    buffer: Option<String>,
+    is_line_comment: bool,
+    can_attach_to_prev: bool,
+}
+
+impl Collector {
+    fn new() -> Collector {
+        Collector {
+            comments: Comments::default(),
+            buffer: None,
+            is_line_comment: false,
+            can_attach_to_prev: true,
+        }
+    }
+
+    fn add_line_comment(&mut self, text: &str) {
+        // Consecutive line comments are combined into a single block, but block comments are not.
+        if self.buffer.is_some() && !self.is_line_comment {
+            self.flush();
+        }
+        self.is_line_comment = true;
+        let buffer = self.buffer.get_or_insert_with(String::new);
+        buffer.push_str(text);
+        buffer.push('\n');
+    }
+
+    fn add_block_comment(&mut self, text: String) {
+        self.flush();
+        self.is_line_comment = false;
+        self.buffer = Some(text);
+    }
+
+    /// Called once the current block is known not to be connected to the next token.
+    fn flush(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            if self.can_attach_to_prev {
+                self.comments.trailing = Some(buffer);
+                self.can_attach_to_prev = false;
+            } else {
+                self.comments.detached.push(buffer);
+            }
+        }
+    }
+
+    fn detach_from_prev(&mut self) {
+        self.can_attach_to_prev = false;
+    }
+
+    /// Called when the next token is on the same line as the comments, in which case it's
+    /// unclear which token they belong to.
+    fn detach_comment(&mut self) {
+        if self.buffer.is_some() {
+            self.can_attach_to_prev = false;
+            self.flush();
+        }
+    }
+
+    fn finish(mut self) -> Comments {
+        // Whatever remains in the buffer leads the next token.
+        self.comments.leading = self.buffer.take();
+        self.comments
+    }
+}
+
+/// Consumes the whitespace and comments following a token, grouping the comments into the
+/// trailing comment of the previous declaration, detached comments, and the leading comment of
+/// the next declaration.
+///
+/// If `at_start` is set, the comments are at the start of the file and there is no previous
+/// declaration.
+pub(super) fn collect(input: Span, at_start: bool) -> IResult<Comments> {
+    let mut collector = Collector::new();
+    let prev_line = input.location_line();
+    let mut trailing_end_line = None;
+    let mut input = input;
+
+    if at_start {
+        collector.detach_from_prev();
+    } else {
+        // A comment on the same line as the previous token is attached to it.
+        let (rest, _) = horizontal_whitespace(input)?;
+        if let Ok((rest, text)) = terminated(line, opt(char('\n')))(rest) {
+            collector.add_line_comment(&text);
+            trailing_end_line = Some(input.location_line());
+            // Don't allow comments on subsequent lines to be attached to the trailing comment.
+            collector.flush();
+            input = rest;
+        } else if let Ok((rest, text)) = block(rest) {
+            collector.add_block_comment(text);
+            trailing_end_line = Some(rest.location_line());
+            let (rest, _) = horizontal_whitespace(rest)?;
+            match char::<_, ()>('\n')(rest) {
+                Ok((rest, _)) => {
+                    collector.flush();
+                    input = rest;
+                }
+                Err(_) => {
+                    // The next token is on the same line, so the comment can't be attributed.
+                    return Ok((rest, Comments::default()));
+                }
+            }
+        } else {
+            match char::<_, ()>('\n')(rest) {
+                Ok((rest, _)) => input = rest,
+                // The next token is on the same line; there are no comments.
+                Err(_) => return Ok((rest, Comments::default())),
+            }
+        }
+    }
+
+    // We are now on the line after the previous token.
+    loop {
+        let (rest, _) = horizontal_whitespace(input)?;
+        if let Ok((rest, text)) = terminated(line, opt(char('\n')))(rest) {
+            collector.add_line_comment(&text);
+            input = rest;
+        } else if let Ok((rest, text)) = block(rest) {
+            collector.add_block_comment(text);
+            // Consume the rest of the line so that it isn't treated as a blank line.
+            let (rest, _) = horizontal_whitespace(rest)?;
+            let (rest, _) = opt(char('\n'))(rest)?;
+            input = rest;
+        } else if let Ok((rest, _)) = char::<_, ()>('\n')(rest) {
+            // A blank line ends the block, and detaches later comments from the previous token.
+            collector.flush();
+            collector.detach_from_prev();
+            input = rest;
+        } else {
+            let next = rest.fragment().chars().next();
+            if matches!(next, None | Some('}') | Some(']') | Some(')')) {
+                // At the end of a scope, comments can't lead the next token.
+                collector.flush();
+            }
+            if next.is_some()
+                && (rest.location_line() == prev_line
+                    || Some(rest.location_line()) == trailing_end_line)
+            {
+                collector.detach_comment();
+            }
+            return Ok((rest, collector.finish()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::location::State;
+
+    fn style(source: &str) -> CommentStyle {
+        parsed(source).0
+    }
+
+    fn parsed(source: &str) -> (CommentStyle, String) {
+        let state = State::default();
+        let (rest, parsed) = parse(Span::new_extra(source, &state)).unwrap();
+        assert!(rest.fragment().is_empty());
+        parsed
+    }
+
+    #[test]
+    fn classifies_comment_styles() {
+        assert_eq!(style("// Foo."), CommentStyle::Line);
+        assert_eq!(style("/// Foo."), CommentStyle::DocLine);
+        assert_eq!(style("//// Foo."), CommentStyle::Line);
+        assert_eq!(style("///"), CommentStyle::DocLine);
+        assert_eq!(style("/* Foo. */"), CommentStyle::Block);
+        assert_eq!(style("/** Foo. */"), CommentStyle::DocBlock);
+        assert_eq!(style("/**\n * Foo.\n */"), CommentStyle::DocBlock);
+        assert_eq!(style("/**/"), CommentStyle::Block);
+        assert_eq!(style("/***/"), CommentStyle::Block);
+        assert_eq!(style("/*** Foo. */"), CommentStyle::Block);
+    }
+
+    #[test]
+    fn keeps_text_directly_after_slashes() {
+        assert_eq!(parsed("//x").1, "x");
+        assert_eq!(parsed("// x").1, " x");
+        assert_eq!(parsed("/*x*/").1, "x");
+    }
+}
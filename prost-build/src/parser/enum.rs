@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use nom::branch::alt;
+use nom::combinator::{cut, map, opt};
+use nom::error::context;
+use nom::multi::many0;
+use nom::sequence::preceded;
+use prost_types::enum_descriptor_proto::EnumReservedRange;
+use prost_types::{EnumDescriptorProto, EnumOptions, EnumValueDescriptorProto, EnumValueOptions};
+
+use super::error::fail;
+use super::location::{end_of_declaration, locate, Tag};
+use super::reserved::{self, Reserved};
+use super::token::{keyword, symbol, ws0};
+use super::{identifier, literal, option, IResult, Span};
+
+/// `FileDescriptorProto.enum_type`
+pub(super) const FILE_TAG: Tag = Tag::Repeated(5);
+/// `DescriptorProto.enum_type`
+pub(super) const MESSAGE_TAG: Tag = Tag::Repeated(4);
+/// `EnumDescriptorProto.name`
+const NAME_TAG: Tag = Tag::Field(1);
+/// `EnumDescriptorProto.value`
+const VALUE_TAG: Tag = Tag::Repeated(2);
+/// `EnumDescriptorProto.options`
+const OPTIONS_TAG: Tag = Tag::Field(3);
+/// `EnumValueDescriptorProto.name`
+const VALUE_NAME_TAG: Tag = Tag::Field(1);
+/// `EnumValueDescriptorProto.number`
+const VALUE_NUMBER_TAG: Tag = Tag::Field(2);
+/// `EnumValueDescriptorProto.options`
+const VALUE_OPTIONS_TAG: Tag = Tag::Field(3);
+
+enum Statement<'a> {
+    Option(EnumOptions),
+    /// An enum value, along with the input it was parsed from.
+    Value(Span<'a>, EnumValueDescriptorProto),
+    Reserved(Reserved),
+    Empty,
+}
+
+/// Parses an `enum` definition, which is stored in the enclosing descriptor's field `tag`.
+pub(super) fn parse<'a>(tag: Tag) -> impl FnMut(Span<'a>) -> IResult<'a, EnumDescriptorProto> {
+    locate(
+        tag,
+        preceded(
+            keyword("enum"),
+            cut(|input| {
+                let (input, name) = preceded(ws0, locate(NAME_TAG, identifier::parse))(input)?;
+                let (input, _) = context("`{`", end_of_declaration('{', true))(input)?;
+                let (input, statements) = many0(statement)(input)?;
+                let (input, _) = context("`}`", end_of_declaration('}', false))(input)?;
+
+                let mut descriptor = EnumDescriptorProto {
+                    name: Some(name),
+                    ..EnumDescriptorProto::default()
+                };
+                let mut value_inputs = Vec::new();
+                for statement in statements {
+                    match statement {
+                        Statement::Option(options) => {
+                            option::merge(&mut descriptor.options, options)
+                        }
+                        Statement::Value(input, value) => {
+                            value_inputs.push(input);
+                            descriptor.value.push(value);
+                        }
+                        Statement::Reserved(Reserved::Ranges(ranges)) => descriptor
+                            .reserved_range
+                            .extend(ranges.into_iter().map(|(start, end)| EnumReservedRange {
+                                start: Some(start),
+                                end: Some(end),
+                            })),
+                        Statement::Reserved(Reserved::Names(names)) => {
+                            descriptor.reserved_name.extend(names)
+                        }
+                        Statement::Empty => (),
+                    }
+                }
+
+                let allow_alias = descriptor
+                    .options
+                    .as_ref()
+                    .map_or(false, |o| o.allow_alias());
+                if !allow_alias {
+                    let mut numbers = HashMap::new();
+                    for (value, input) in descriptor.value.iter().zip(value_inputs) {
+                        if let Some(existing) = numbers.insert(value.number(), value.name()) {
+                            return fail(
+                                input,
+                                format!(
+                                    "\"{}\" uses the same enum value as \"{}\"; set `option allow_alias = true;` to allow this",
+                                    value.name(),
+                                    existing
+                                ),
+                            );
+                        }
+                    }
+                }
+                Ok((input, descriptor))
+            }),
+        ),
+    )
+}
+
+fn statement(input: Span) -> IResult<Statement> {
+    alt((
+        map(
+            option::parse_statement::<EnumOptions>(OPTIONS_TAG),
+            Statement::Option,
+        ),
+        map(reserved::parse_enum, Statement::Reserved),
+        map(end_of_declaration(';', false), |()| Statement::Empty),
+        map(enum_value, |(input, value)| Statement::Value(input, value)),
+    ))(input)
+}
+
+/// Parses an enum value, such as `FOO = 1;`, returning it along with the input it starts at.
+fn enum_value(input: Span) -> IResult<(Span, EnumValueDescriptorProto)> {
+    locate(VALUE_TAG, |start| {
+        let input = start;
+        let (input, name) = locate(VALUE_NAME_TAG, identifier::parse)(input)?;
+        let (input, _) = symbol('=')(input)?;
+        let (input, number) = cut(preceded(
+            ws0,
+            context(
+                "enum value number",
+                locate(VALUE_NUMBER_TAG, literal::int32),
+            ),
+        ))(input)?;
+        let (input, options) = opt(preceded(
+            ws0,
+            option::parse_list::<EnumValueOptions>(VALUE_OPTIONS_TAG),
+        ))(input)?;
+        let (input, _) = cut(context("`;`", end_of_declaration(';', true)))(input)?;
+        Ok((
+            input,
+            (
+                start,
+                EnumValueDescriptorProto {
+                    name: Some(name),
+                    number: Some(number),
+                    options,
+                },
+            ),
+        ))
+    })(input)
+}
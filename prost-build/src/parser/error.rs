@@ -0,0 +1,282 @@
+use std::{error, fmt, io};
+
+use nom::error::{ContextError, ErrorKind, FromExternalError, ParseError as NomParseError};
+use prost_types::FileDescriptorProto;
+
+use super::{start_of, IResult, Span};
+
+/// An error which prevented a set of `.proto` files from being parsed.
+#[derive(Debug)]
+pub enum Error {
+    /// A file isn't syntactically valid.
+    Parse(ParseError),
+    /// A file refers to something which isn't defined, or the files can't be put in dependency
+    /// order.
+    Resolution(ResolutionError),
+    /// The files were resolved, but break rules which `protoc` enforces.
+    Validation(Vec<ValidationError>),
+    /// A file couldn't be read.
+    Io(io::Error),
+}
+
+/// A syntax error in a `.proto` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The name of the file containing the error.
+    pub file: String,
+    /// The one-based line at which the error occurred.
+    pub line: usize,
+    /// The one-based column at which the error occurred, counted in characters.
+    pub column: usize,
+    /// A description of the error.
+    pub message: String,
+}
+
+/// An error resolving the imports or type references of a `.proto` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolutionError {
+    /// The name of the file containing the error.
+    pub file: String,
+    /// The one-based line of the element responsible, or zero if there's no such element.
+    pub line: usize,
+    /// The one-based column of the element responsible, or zero if there's no such element.
+    pub column: usize,
+    /// A description of the error.
+    pub message: String,
+}
+
+/// A resolved element of a `.proto` file which `protoc` would reject.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The name of the file containing the error.
+    pub file: String,
+    /// The one-based line of the element responsible, or zero if its location isn't known.
+    pub line: usize,
+    /// The one-based column of the element responsible, or zero if its location isn't known.
+    pub column: usize,
+    /// A description of the error.
+    pub message: String,
+}
+
+impl ResolutionError {
+    /// Creates an error concerning the element of `file` at `path`.
+    pub(super) fn at(file: &FileDescriptorProto, path: &[i32], message: String) -> Self {
+        let (line, column) = start_of(file, path).unwrap_or_default();
+        ResolutionError {
+            file: file.name().to_owned(),
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+impl ValidationError {
+    /// Creates an error concerning the element of `file` at `path`.
+    pub(super) fn at(file: &FileDescriptorProto, path: &[i32], message: String) -> Self {
+        let (line, column) = start_of(file, path).unwrap_or_default();
+        ValidationError {
+            file: file.name().to_owned(),
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+/// Writes an error message prefixed with its position, omitting the line and column if they're
+/// unknown.
+fn write_located(
+    f: &mut fmt::Formatter,
+    file: &str,
+    line: usize,
+    column: usize,
+    message: &str,
+) -> fmt::Result {
+    if line == 0 {
+        write!(f, "{}: {}", file, message)
+    } else {
+        write!(f, "{}:{}:{}: {}", file, line, column, message)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(error) => error.fmt(f),
+            Error::Resolution(error) => error.fmt(f),
+            Error::Validation(errors) => {
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("\n")?;
+                    }
+                    error.fmt(f)?;
+                }
+                Ok(())
+            }
+            Error::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_located(f, &self.file, self.line, self.column, &self.message)
+    }
+}
+
+impl fmt::Display for ResolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_located(f, &self.file, self.line, self.column, &self.message)
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_located(f, &self.file, self.line, self.column, &self.message)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+impl error::Error for ResolutionError {}
+impl error::Error for ValidationError {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+/// Converts the error for use with the rest of `prost-build`, which reports errors as
+/// `io::Error`s.
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(error) => error,
+            error => io::Error::new(io::ErrorKind::InvalidInput, error),
+        }
+    }
+}
+
+/// An error produced by the parsers while parsing a `.proto` file.
+///
+/// Errors carry the input at which they occurred so that they can be reported with a line and
+/// column.
+#[derive(Debug)]
+pub(super) struct InputError<'a> {
+    pub(super) input: Span<'a>,
+    pub(super) message: Message,
+}
+
+/// The number of characters of leftover input quoted in an error.
+const SNIPPET_LENGTH: usize = 30;
+
+/// Describes what went wrong while parsing.
+#[derive(Debug)]
+pub(super) enum Message {
+    /// A low-level `nom` error, for which no better description is available.
+    Kind(ErrorKind),
+    /// The input did not contain the expected syntactic element.
+    Expected(&'static str),
+    /// A descriptive error message.
+    Custom(String),
+}
+
+impl<'a> InputError<'a> {
+    /// Returns the one-based line and column at which the error occurred.
+    pub(super) fn line_column(&self) -> (u32, usize) {
+        (self.input.location_line(), self.input.get_utf8_column())
+    }
+}
+
+impl fmt::Display for InputError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.message {
+            // Input was left over once parsing stopped, so show what it begins with to make it
+            // easier to find.
+            Message::Kind(ErrorKind::Eof) => {
+                let line = self.input.fragment().lines().next().unwrap_or_default();
+                let snippet: String = line.trim_end().chars().take(SNIPPET_LENGTH).collect();
+                let ellipsis = if snippet.len() < line.trim_end().len() {
+                    "..."
+                } else {
+                    ""
+                };
+                write!(f, "unexpected input \"{}{}\"", snippet, ellipsis)
+            }
+            _ => self.message.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Kind(ErrorKind::Eof) => f.write_str("unexpected input"),
+            Message::Kind(kind) => write!(f, "syntax error ({})", kind.description()),
+            Message::Expected(expected) => write!(f, "expected {}", expected),
+            Message::Custom(message) => f.write_str(message),
+        }
+    }
+}
+
+impl<'a> NomParseError<Span<'a>> for InputError<'a> {
+    fn from_error_kind(input: Span<'a>, kind: ErrorKind) -> Self {
+        InputError {
+            input,
+            message: Message::Kind(kind),
+        }
+    }
+
+    fn append(_: Span<'a>, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        // Report the alternative that made it furthest into the input, since it's most likely to
+        // be the one the author intended.
+        if other.input.location_offset() >= self.input.location_offset() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+impl<'a> ContextError<Span<'a>> for InputError<'a> {
+    fn add_context(_: Span<'a>, context: &'static str, mut other: Self) -> Self {
+        if let Message::Kind(_) = other.message {
+            other.message = Message::Expected(context);
+        }
+        other
+    }
+}
+
+impl<'a, E> FromExternalError<Span<'a>, E> for InputError<'a>
+where
+    E: fmt::Display,
+{
+    fn from_external_error(input: Span<'a>, _: ErrorKind, error: E) -> Self {
+        InputError {
+            input,
+            message: Message::Custom(error.to_string()),
+        }
+    }
+}
+
+/// Fails unrecoverably at `input` with a descriptive message.
+pub(super) fn fail<'a, O>(input: Span<'a>, message: impl Into<String>) -> IResult<'a, O> {
+    Err(nom::Err::Failure(InputError {
+        input,
+        message: Message::Custom(message.into()),
+    }))
+}
@@ -0,0 +1,50 @@
+use nom::branch::alt;
+use nom::combinator::{cut, map};
+use nom::error::context;
+use nom::multi::many0;
+use nom::sequence::preceded;
+use prost_types::FieldDescriptorProto;
+
+use super::location::{end_of_declaration, locate, Tag};
+use super::token::{keyword, ws0};
+use super::{field, identifier, IResult, Span};
+
+/// `FileDescriptorProto.extension`
+pub(super) const FILE_TAG: i32 = 7;
+/// `DescriptorProto.extension`
+pub(super) const MESSAGE_TAG: i32 = 6;
+
+/// Parses an `extend` block, returning the extension fields it declares with their `extendee`
+/// set.
+///
+/// The block itself is located at the enclosing descriptor's field `number`, while each of its
+/// fields is an element of that field, as with `protoc`.
+pub(super) fn parse<'a>(
+    number: i32,
+) -> impl FnMut(Span<'a>) -> IResult<'a, Vec<FieldDescriptorProto>> {
+    locate(
+        Tag::Field(number),
+        preceded(
+            keyword("extend"),
+            cut(move |input| {
+                let (input, extendee) = preceded(ws0, identifier::parse_type)(input)?;
+                let (input, _) = context("`{`", end_of_declaration('{', true))(input)?;
+                let (input, fields) = many0(alt((
+                    map(field::parse_extension(Tag::Repeated(number)), Some),
+                    map(end_of_declaration(';', false), |()| None),
+                )))(input)?;
+                let (input, _) = context("`}`", end_of_declaration('}', false))(input)?;
+
+                let fields = fields
+                    .into_iter()
+                    .flatten()
+                    .map(|field| FieldDescriptorProto {
+                        extendee: Some(extendee.clone()),
+                        ..field
+                    })
+                    .collect();
+                Ok((input, fields))
+            }),
+        ),
+    )
+}
@@ -0,0 +1,653 @@
+use nom::branch::alt;
+use nom::character::complete::char;
+use nom::combinator::{cut, map, map_res, not, opt, peek, value};
+use nom::error::context;
+use nom::sequence::{pair, preceded, terminated};
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::{DescriptorProto, FieldDescriptorProto, FieldOptions, MessageOptions};
+
+use super::error::fail;
+use super::location::{end_of_declaration, locate, outer, Tag};
+use super::option;
+use super::reserved::MAX_FIELD_NUMBER;
+use super::syntax::Syntax;
+use super::token::{self, keyword, symbol, ws0};
+use super::{identifier, literal, message, IResult, Span};
+
+/// `DescriptorProto.field`
+const TAG: Tag = Tag::Repeated(2);
+/// `FieldDescriptorProto.name`
+const NAME_TAG: Tag = Tag::Field(1);
+/// `FieldDescriptorProto.number`
+const NUMBER_TAG: Tag = Tag::Field(3);
+/// `FieldDescriptorProto.label`
+const LABEL_TAG: Tag = Tag::Field(4);
+/// `FieldDescriptorProto.type`
+const TYPE_TAG: Tag = Tag::Field(5);
+/// `FieldDescriptorProto.type_name`
+const TYPE_NAME_TAG: Tag = Tag::Field(6);
+/// `FieldDescriptorProto.options`
+const OPTIONS_TAG: Tag = Tag::Field(8);
+/// `FieldDescriptorProto.default_value`
+const DEFAULT_TAG: Tag = Tag::Field(7);
+/// `FieldDescriptorProto.json_name`
+const JSON_NAME_TAG: Tag = Tag::Field(10);
+
+/// An entry in a field's option list which sets the field descriptor itself, rather than its
+/// options.
+enum PseudoOption {
+    Default(String),
+    JsonName(String),
+}
+
+/// The type of a field, as written in its declaration.
+enum FieldType {
+    Named(String),
+    /// A `map<K, V>` type, with the names of its key and value types.
+    Map(String, String),
+}
+
+/// Parses a field declaration, such as `repeated string names = 1 [deprecated = true];`.
+///
+/// Map fields and groups are returned along with their synthetic map entry or group message,
+/// which the caller must add to the enclosing message's nested types.
+pub(super) fn parse(input: Span) -> IResult<(FieldDescriptorProto, Option<DescriptorProto>)> {
+    locate(TAG, alt((group, field(true))))(input)
+}
+
+/// Parses a field declared within a `oneof`, which has no label.
+///
+/// The field is located within the enclosing message rather than the `oneof`.
+pub(super) fn parse_oneof_field(input: Span) -> IResult<FieldDescriptorProto> {
+    map(outer(locate(TAG, field(false))), |(field, _)| field)(input)
+}
+
+/// Parses a field declared within an `extend` block, which is stored in the enclosing
+/// descriptor's field `tag`.
+pub(super) fn parse_extension<'a>(
+    tag: Tag,
+) -> impl FnMut(Span<'a>) -> IResult<'a, FieldDescriptorProto> {
+    move |input| {
+        let (rest, (field, entry)) = outer(locate(tag, field(true)))(input)?;
+        if entry.is_some() {
+            return fail(input, "map fields are not allowed to be extensions");
+        }
+        Ok((rest, field))
+    }
+}
+
+fn field<'a>(
+    labelled: bool,
+) -> impl FnMut(Span<'a>) -> IResult<'a, (FieldDescriptorProto, Option<DescriptorProto>)> {
+    move |start: Span<'a>| {
+        let syntax = start.extra.syntax();
+        let (input, label) = if labelled {
+            opt(terminated(locate(LABEL_TAG, label), ws0))(start)?
+        } else {
+            (start, None)
+        };
+        if syntax == Syntax::Proto3 && label == Some(Label::Required) {
+            return fail(start, "required fields are not allowed in proto3");
+        }
+        // As with `protoc`, a scalar type is located at the field's `type`, and a reference to
+        // another type, including a map's entry type, at its `type_name`.
+        let type_tag = match peek(scalar_type)(input) {
+            Ok((_, Some(_))) => TYPE_TAG,
+            _ => TYPE_NAME_TAG,
+        };
+        let (input, field_type) = locate(type_tag, field_type)(input)?;
+        let (input, _) = ws0(input)?;
+        let (input, name) = locate(NAME_TAG, identifier::parse)(input)?;
+        let (input, _) = cut(context("`=`", symbol('=')))(input)?;
+
+        let (type_name, entry) = match field_type {
+            FieldType::Named(type_name) => (type_name, None),
+            FieldType::Map(_, _) if label.is_some() => {
+                return fail(
+                    start,
+                    "field labels (required/optional/repeated) are not allowed on map fields",
+                )
+            }
+            FieldType::Map(_, _) if !labelled => {
+                return fail(start, "map fields are not allowed in oneofs")
+            }
+            FieldType::Map(key, value) => {
+                let entry = map_entry(&name, key, value);
+                (entry.name().to_owned(), Some(entry))
+            }
+        };
+        if entry.is_some() {
+            // The entry is a nested type, so later nested types follow it.
+            outer(|input: Span<'a>| {
+                input.extra.reserve_index(message::NESTED_TAG);
+                Ok((input, ()))
+            })(input)?;
+        }
+        let r#type = scalar(&type_name);
+
+        let proto3_optional = syntax == Syntax::Proto3 && label == Some(Label::Optional);
+        let label = match (label, syntax) {
+            (Some(label), _) => label,
+            (None, _) if entry.is_some() => Label::Repeated,
+            (None, Syntax::Proto3) => Label::Optional,
+            (None, Syntax::Proto2) if !labelled => Label::Optional,
+            (None, Syntax::Proto2) => {
+                return fail(
+                    start,
+                    "expected \"required\", \"optional\", or \"repeated\"",
+                )
+            }
+        };
+
+        let (input, _) = ws0(input)?;
+        let (input, number) = locate(NUMBER_TAG, number)(input)?;
+        let (input, _) = ws0(input)?;
+        let (input, options) = opt(option::parse_list_with::<FieldOptions, _, _>(
+            OPTIONS_TAG,
+            pseudo_option(r#type),
+        ))(input)?;
+        let (input, _) = cut(context("`;`", end_of_declaration(';', true)))(input)?;
+
+        let (options, pseudo_options) = options.unwrap_or_default();
+        let mut json_name = to_json_name(&name);
+        let mut default_value = None;
+        for pseudo_option in pseudo_options {
+            match pseudo_option {
+                PseudoOption::Default(value) => default_value = Some(value),
+                PseudoOption::JsonName(name) => json_name = name,
+            }
+        }
+
+        let (r#type, type_name) = type_and_name(type_name);
+        let field = FieldDescriptorProto {
+            json_name: Some(json_name),
+            name: Some(name),
+            number: Some(number),
+            label: Some(label as i32),
+            r#type,
+            type_name,
+            default_value,
+            options,
+            proto3_optional: if proto3_optional { Some(true) } else { None },
+            ..FieldDescriptorProto::default()
+        };
+        Ok((input, (field, entry)))
+    }
+}
+
+/// Parses a proto2 group, such as `repeated group Result = 1 { optional string url = 2; }`.
+///
+/// A group declares both a field and a nested message type. As with `protoc`, the message is
+/// located at the same span as the field, and comments are attached to the message.
+fn group(start: Span) -> IResult<(FieldDescriptorProto, Option<DescriptorProto>)> {
+    // Check that this is a group before recording the locations of its parts.
+    peek(pair(terminated(label, ws0), keyword("group")))(start)?;
+    if start.extra.syntax() == Syntax::Proto3 {
+        return fail(start, "groups are not supported in proto3 syntax");
+    }
+    let (input, label) = terminated(locate(LABEL_TAG, label), ws0)(start)?;
+    let (input, _) = locate(TYPE_TAG, keyword("group"))(input)?;
+    let (input, _) = ws0(input)?;
+    let (rest, name) = cut(locate(NAME_TAG, identifier::parse))(input)?;
+    if !name.starts_with(|c: char| c.is_ascii_uppercase()) {
+        return fail(input, "group names must start with a capital letter");
+    }
+    let (input, _) = cut(context("`=`", symbol('=')))(rest)?;
+    let (input, _) = ws0(input)?;
+    let (input, number) = locate(NUMBER_TAG, number)(input)?;
+    let (input, _) = ws0(input)?;
+    let (input, options) = opt(option::parse_list_with::<FieldOptions, _, _>(
+        OPTIONS_TAG,
+        pseudo_option(Some(Type::Group)),
+    ))(input)?;
+    // The message's location starts at the start of the field, before its label.
+    let (input, message) = outer(locate(message::NESTED_TAG, |_| {
+        cut(message::body(name.clone()))(input)
+    }))(start)?;
+
+    let (options, pseudo_options) = options.unwrap_or_default();
+    let field_name = name.to_ascii_lowercase();
+    let mut json_name = to_json_name(&field_name);
+    for pseudo_option in pseudo_options {
+        match pseudo_option {
+            PseudoOption::Default(_) => unreachable!("groups can't have default values"),
+            PseudoOption::JsonName(name) => json_name = name,
+        }
+    }
+    let field = FieldDescriptorProto {
+        json_name: Some(json_name),
+        name: Some(field_name),
+        number: Some(number),
+        label: Some(label as i32),
+        r#type: Some(Type::Group as i32),
+        type_name: Some(name),
+        options,
+        ..FieldDescriptorProto::default()
+    };
+    Ok((input, (field, Some(message))))
+}
+
+/// Parses a field's type, which is either a type name or a `map<K, V>` type.
+///
+/// As with `protoc`, `map` is only treated as a keyword when followed by `<`, so that it can
+/// still be used as a message name.
+fn field_type(input: Span) -> IResult<FieldType> {
+    alt((
+        preceded(
+            pair(keyword("map"), symbol('<')),
+            cut(|input| {
+                let (input, _) = ws0(input)?;
+                let (rest, key) = type_name(input)?;
+                match scalar(&key) {
+                    Some(Type::Float | Type::Double | Type::Bytes) | None => {
+                        return fail(
+                            input,
+                            "key in map fields cannot be float/double, bytes or message types",
+                        )
+                    }
+                    Some(_) => (),
+                }
+                let (input, _) = context("`,`", symbol(','))(rest)?;
+                let (input, _) = ws0(input)?;
+                let (input, value) = type_name(input)?;
+                let (input, _) = context("`>`", symbol('>'))(input)?;
+                Ok((input, FieldType::Map(key, value)))
+            }),
+        ),
+        map(type_name, FieldType::Named),
+    ))(input)
+}
+
+/// Parses the name of a field's type, replacing any alias registered with the parser by the name
+/// of the scalar type it stands for.
+fn type_name(input: Span) -> IResult<String> {
+    match scalar_type(input)? {
+        (rest, Some(r#type)) => {
+            let &(name, _) = SCALARS
+                .iter()
+                .find(|&&(_, scalar)| scalar == r#type)
+                .expect("every scalar type has a name");
+            Ok((rest, name.to_owned()))
+        }
+        (_, None) => identifier::parse_type(input),
+    }
+}
+
+/// Parses a scalar type, such as `int32`, or an alias registered with the parser for one.
+///
+/// Returns `None` without consuming any input if the type isn't a scalar, in which case it's a
+/// reference to a message or enum. As with `protoc`, only a whole type name is a scalar, so
+/// neither `stringValue` nor `string.Value` is.
+fn scalar_type(input: Span) -> IResult<Option<Type>> {
+    let builtin = alt((
+        value(Type::Double, keyword("double")),
+        value(Type::Float, keyword("float")),
+        value(Type::Int32, keyword("int32")),
+        value(Type::Int64, keyword("int64")),
+        value(Type::Uint32, keyword("uint32")),
+        value(Type::Uint64, keyword("uint64")),
+        value(Type::Sint32, keyword("sint32")),
+        value(Type::Sint64, keyword("sint64")),
+        value(Type::Fixed32, keyword("fixed32")),
+        value(Type::Fixed64, keyword("fixed64")),
+        value(Type::Sfixed32, keyword("sfixed32")),
+        value(Type::Sfixed64, keyword("sfixed64")),
+        value(Type::Bool, keyword("bool")),
+        value(Type::String, keyword("string")),
+        value(Type::Bytes, keyword("bytes")),
+    ));
+    let (rest, r#type) = opt(terminated(builtin, not(peek(char('.')))))(input)?;
+    if r#type.is_some() {
+        return Ok((rest, r#type));
+    }
+    match identifier::parse_type(input) {
+        Ok((rest, name)) => match input.extra.scalar_alias(&name) {
+            Some(r#type) => Ok((rest, Some(r#type))),
+            None => Ok((input, None)),
+        },
+        Err(_) => Ok((input, None)),
+    }
+}
+
+/// Builds the synthetic message type holding the entries of the map field `field_name`, in the
+/// same way as `protoc`.
+fn map_entry(field_name: &str, key: String, value: String) -> DescriptorProto {
+    let mut name = String::with_capacity(field_name.len() + 5);
+    let mut capitalize_next = true;
+    for c in field_name.chars() {
+        if c == '_' {
+            capitalize_next = true;
+        } else if capitalize_next {
+            name.push(c.to_ascii_uppercase());
+            capitalize_next = false;
+        } else {
+            name.push(c);
+        }
+    }
+    name.push_str("Entry");
+
+    let entry_field = |name: &str, number, type_name| {
+        let (r#type, type_name) = type_and_name(type_name);
+        FieldDescriptorProto {
+            name: Some(name.to_owned()),
+            json_name: Some(name.to_owned()),
+            number: Some(number),
+            label: Some(Label::Optional as i32),
+            r#type,
+            type_name,
+            ..FieldDescriptorProto::default()
+        }
+    };
+    DescriptorProto {
+        name: Some(name),
+        field: vec![entry_field("key", 1, key), entry_field("value", 2, value)],
+        options: Some(MessageOptions {
+            map_entry: Some(true),
+            ..MessageOptions::default()
+        }),
+        ..DescriptorProto::default()
+    }
+}
+
+/// Returns the `type` and `type_name` of a field with the given type.
+///
+/// References to messages and enums have no `type` until they're resolved.
+fn type_and_name(type_name: String) -> (Option<i32>, Option<String>) {
+    match scalar(&type_name) {
+        Some(r#type) => (Some(r#type as i32), None),
+        None => (None, Some(type_name)),
+    }
+}
+
+/// Parses a pseudo-option, such as `default = 5` or `json_name = "foo"`.
+///
+/// Pseudo-options are located relative to the field, rather than its options. The syntax of a
+/// default value depends on the field's type, which is `None` if it refers to a message or enum.
+fn pseudo_option<'a>(r#type: Option<Type>) -> impl FnMut(Span<'a>) -> IResult<'a, PseudoOption> {
+    move |input| {
+        alt((
+            outer(locate(
+                DEFAULT_TAG,
+                preceded(
+                    keyword("default"),
+                    cut(|input| {
+                        let (input, _) = context("`=`", symbol('='))(input)?;
+                        let (input, _) = ws0(input)?;
+                        let (input, value) = default_value(r#type)(input)?;
+                        Ok((input, PseudoOption::Default(value)))
+                    }),
+                ),
+            )),
+            outer(locate(
+                JSON_NAME_TAG,
+                preceded(
+                    keyword("json_name"),
+                    cut(|input| {
+                        let (input, _) = context("`=`", symbol('='))(input)?;
+                        let (input, _) = ws0(input)?;
+                        let (input, json_name) =
+                            context("string", map_res(literal::string, String::from_utf8))(input)?;
+                        Ok((input, PseudoOption::JsonName(json_name)))
+                    }),
+                ),
+            )),
+        ))(input)
+    }
+}
+
+/// Parses a field's default value, returning it as `protoc` stores it in `default_value`.
+///
+/// Enum defaults are kept as the bare value name; whether it names a value of the field's enum is
+/// only checked once the field's type has been resolved.
+fn default_value<'a>(r#type: Option<Type>) -> impl FnMut(Span<'a>) -> IResult<'a, String> {
+    move |input| match r#type {
+        None => map(token::any, |token: Span| token.fragment().to_string())(input),
+        Some(Type::Int32 | Type::Sint32 | Type::Sfixed32) => signed(i32::MAX as u64)(input),
+        Some(Type::Int64 | Type::Sint64 | Type::Sfixed64) => signed(i64::MAX as u64)(input),
+        Some(Type::Uint32 | Type::Fixed32) => unsigned(u32::MAX as u64)(input),
+        Some(Type::Uint64 | Type::Fixed64) => unsigned(u64::MAX)(input),
+        Some(r#type @ (Type::Float | Type::Double)) => map(
+            pair(
+                opt(terminated(symbol('-'), ws0)),
+                context(
+                    "number",
+                    alt((
+                        literal::float,
+                        map(literal::int, |value| value as f64),
+                        value(f64::INFINITY, keyword("inf")),
+                        value(f64::NAN, keyword("nan")),
+                    )),
+                ),
+            ),
+            move |(sign, value)| {
+                let value = if sign.is_some() { -value } else { value };
+                format_float(value, r#type)
+            },
+        )(input),
+        Some(Type::Bool) => context(
+            "`true` or `false`",
+            alt((
+                value("true".to_owned(), keyword("true")),
+                value("false".to_owned(), keyword("false")),
+            )),
+        )(input),
+        Some(Type::String) => context("string", map_res(literal::string, String::from_utf8))(input),
+        Some(Type::Bytes) => {
+            context("string", map(literal::string, |bytes| c_escape(&bytes)))(input)
+        }
+        Some(Type::Group) => fail(input, "messages can't have default values"),
+        Some(Type::Enum | Type::Message) => unreachable!("scalar() never returns a named type"),
+    }
+}
+
+/// Parses a possibly negative integer default, which must fit in a signed type with the given
+/// maximum value.
+fn signed<'a>(max: u64) -> impl FnMut(Span<'a>) -> IResult<'a, String> {
+    move |input| {
+        let (input, sign) = opt(terminated(symbol('-'), ws0))(input)?;
+        // Two's complement has one more negative value than positive.
+        let max = if sign.is_some() { max + 1 } else { max };
+        let (input, value) = in_range(max)(input)?;
+        let sign = if sign.is_some() { "-" } else { "" };
+        Ok((input, format!("{}{}", sign, value)))
+    }
+}
+
+/// Parses an integer default for an unsigned type with the given maximum value.
+fn unsigned<'a>(max: u64) -> impl FnMut(Span<'a>) -> IResult<'a, String> {
+    move |input| {
+        if let Ok((_, '-')) = symbol('-')(input) {
+            return fail(input, "unsigned field can't have negative default value");
+        }
+        map(in_range(max), |value| value.to_string())(input)
+    }
+}
+
+fn in_range<'a>(max: u64) -> impl FnMut(Span<'a>) -> IResult<'a, u64> {
+    move |input| {
+        let (rest, value) = literal::int(input)?;
+        if value > max {
+            return fail(input, "integer out of range");
+        }
+        Ok((rest, value))
+    }
+}
+
+/// Formats a floating point default of the given type in the same way as `protoc`.
+///
+/// As with `protoc`, the shortest of the `%g` formats with 6 and 9 significant digits for a
+/// `float`, or 15 and 17 for a `double`, which represents the value exactly is used.
+fn format_float(value: f64, r#type: Type) -> String {
+    if value.is_nan() {
+        return "nan".to_owned();
+    } else if value.is_infinite() {
+        return if value < 0.0 { "-inf" } else { "inf" }.to_owned();
+    }
+    if r#type == Type::Float {
+        let value = value as f32;
+        let short = format_g(value as f64, 6);
+        if short.parse::<f32>() == Ok(value) {
+            short
+        } else {
+            format_g(value as f64, 9)
+        }
+    } else {
+        let short = format_g(value, 15);
+        if short.parse::<f64>() == Ok(value) {
+            short
+        } else {
+            format_g(value, 17)
+        }
+    }
+}
+
+/// Formats a finite value like C's `%.*g`, with the given number of significant digits.
+fn format_g(value: f64, precision: usize) -> String {
+    let scientific = format!("{:.*e}", precision - 1, value);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let trim = |digits: &str| {
+        if digits.contains('.') {
+            digits
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_owned()
+        } else {
+            digits.to_owned()
+        }
+    };
+    if exponent < -4 || exponent >= precision as i32 {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", trim(mantissa), sign, exponent.abs())
+    } else {
+        let decimals = (precision as i32 - 1 - exponent) as usize;
+        trim(&format!("{:.*}", decimals, value))
+    }
+}
+
+/// Escapes a `bytes` default value in the same way as `protoc`, using octal escapes for
+/// non-printable bytes.
+fn c_escape(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for &b in bytes {
+        match b {
+            b'\n' => escaped.push_str("\\n"),
+            b'\r' => escaped.push_str("\\r"),
+            b'\t' => escaped.push_str("\\t"),
+            b'"' => escaped.push_str("\\\""),
+            b'\'' => escaped.push_str("\\'"),
+            b'\\' => escaped.push_str("\\\\"),
+            0x20..=0x7e => escaped.push(b as char),
+            _ => escaped.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    escaped
+}
+
+/// Parses a field number, which must be between 1 and [`MAX_FIELD_NUMBER`].
+fn number(input: Span) -> IResult<i32> {
+    let (rest, number) = cut(context("field number", literal::int))(input)?;
+    match i32::try_from(number) {
+        Ok(number @ 1..=MAX_FIELD_NUMBER) => Ok((rest, number)),
+        Ok(0) => fail(input, "field numbers must be positive integers"),
+        _ => fail(
+            input,
+            format!("field numbers cannot be greater than {}", MAX_FIELD_NUMBER),
+        ),
+    }
+}
+
+fn label(input: Span) -> IResult<Label> {
+    alt((
+        value(Label::Optional, keyword("optional")),
+        value(Label::Required, keyword("required")),
+        value(Label::Repeated, keyword("repeated")),
+    ))(input)
+}
+
+/// The scalar types, keyed by the name they are declared with.
+const SCALARS: [(&str, Type); 15] = [
+    ("double", Type::Double),
+    ("float", Type::Float),
+    ("int32", Type::Int32),
+    ("int64", Type::Int64),
+    ("uint32", Type::Uint32),
+    ("uint64", Type::Uint64),
+    ("sint32", Type::Sint32),
+    ("sint64", Type::Sint64),
+    ("fixed32", Type::Fixed32),
+    ("fixed64", Type::Fixed64),
+    ("sfixed32", Type::Sfixed32),
+    ("sfixed64", Type::Sfixed64),
+    ("bool", Type::Bool),
+    ("string", Type::String),
+    ("bytes", Type::Bytes),
+];
+
+/// Returns the scalar type with the given name, if any.
+fn scalar(name: &str) -> Option<Type> {
+    SCALARS
+        .iter()
+        .find(|&&(scalar, _)| scalar == name)
+        .map(|&(_, r#type)| r#type)
+}
+
+/// Returns the scalar type name which `name`, an undefined type name, was most likely meant to be.
+///
+/// Only single lowercase words are considered, since message and enum names are conventionally
+/// capitalized. A scalar type name is suggested if either name starts with the other, such as
+/// `int` for `int32` or `boolean` for `bool`, or if it is at most two edits away.
+pub(super) fn similar_scalar(name: &str) -> Option<&'static str> {
+    if !name.starts_with(|c: char| c.is_ascii_lowercase())
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    {
+        return None;
+    }
+    let names = SCALARS.iter().map(|&(scalar, _)| scalar);
+    if let Some(scalar) = names
+        .clone()
+        .find(|scalar| scalar.starts_with(name) || name.starts_with(scalar))
+    {
+        return Some(scalar);
+    }
+    names
+        .map(|scalar| (edit_distance(name, scalar), scalar))
+        .filter(|&(distance, _)| distance <= 2)
+        .min()
+        .map(|(_, scalar)| scalar)
+}
+
+/// Returns the number of single character insertions, deletions and substitutions needed to turn
+/// `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != y);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Converts a field name to its default JSON name, in the same way as `protoc`.
+pub(super) fn to_json_name(name: &str) -> String {
+    let mut json_name = String::with_capacity(name.len());
+    let mut capitalize_next = false;
+    for c in name.chars() {
+        if c == '_' {
+            capitalize_next = true;
+        } else if capitalize_next {
+            json_name.push(c.to_ascii_uppercase());
+            capitalize_next = false;
+        } else {
+            json_name.push(c);
+        }
+    }
+    json_name
+}
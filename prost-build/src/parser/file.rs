@@ -0,0 +1,191 @@
+use nom::branch::alt;
+use nom::combinator::{fail as fail_parser, iterator, map, peek};
+use nom::error::context;
+use prost_types::{
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileOptions,
+    ServiceDescriptorProto,
+};
+
+use super::error::fail;
+use super::import::{self, ImportKind};
+use super::location::{end_of_declaration, Tag};
+use super::r#enum;
+use super::syntax::Syntax;
+use super::token::{self, symbol, ws0};
+use super::{
+    comment, extend, identifier, message, option, package, service, syntax, IResult, Span,
+};
+
+/// `FileDescriptorProto.options`
+const OPTIONS_TAG: Tag = Tag::Field(8);
+
+/// A top-level statement of a `.proto` file, following its `syntax` statement.
+///
+/// Type references within the statement are left unresolved.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    /// A `package` statement, with the package name.
+    Package(String),
+    /// An `import` statement, with the imported path.
+    Import(String, ImportKind),
+    /// A file-level `option` statement.
+    Option(FileOptions),
+    /// A `message` definition.
+    Message(DescriptorProto),
+    /// An `enum` definition.
+    Enum(EnumDescriptorProto),
+    /// A `service` definition.
+    Service(ServiceDescriptorProto),
+    /// An `extend` block, with the extension fields it declares.
+    Extend(Vec<FieldDescriptorProto>),
+}
+
+/// Parses the contents of a `.proto` file.
+///
+/// The file's name and source code info are left for the caller to fill in.
+pub(super) fn parse(input: Span) -> IResult<FileDescriptorProto> {
+    let (input, (syntax, statements)) = parse_statements(input)?;
+
+    let mut file = FileDescriptorProto {
+        syntax: Some(syntax.to_string()),
+        ..FileDescriptorProto::default()
+    };
+    for (statement, _) in statements {
+        match statement {
+            Statement::Package(package) => file.package = Some(package),
+            Statement::Import(path, kind) => {
+                let index = file.dependency.len() as i32;
+                file.dependency.push(path);
+                match kind {
+                    ImportKind::Default => (),
+                    ImportKind::Public => file.public_dependency.push(index),
+                    ImportKind::Weak => file.weak_dependency.push(index),
+                }
+            }
+            Statement::Option(options) => option::merge(&mut file.options, options),
+            Statement::Message(message) => file.message_type.push(message),
+            Statement::Enum(r#enum) => file.enum_type.push(r#enum),
+            Statement::Service(service) => file.service.push(service),
+            Statement::Extend(fields) => file.extension.extend(fields),
+        }
+    }
+    Ok((input, file))
+}
+
+/// Parses the `syntax` statement of a `.proto` file and the statements following it.
+///
+/// Each statement is returned along with the index of its location among those recorded in the
+/// state. Empty and skipped statements are left out.
+pub(super) fn parse_statements(input: Span) -> IResult<(Syntax, Vec<(Statement, usize)>)> {
+    let (input, syntax) = start(input)?;
+    let mut statements = iterator(input, located_statement);
+    let parsed = (&mut statements).flatten().collect();
+    let (input, ()) = statements.finish()?;
+    let (input, ()) = end(input)?;
+    Ok((input, (syntax, parsed)))
+}
+
+/// Parses the start of a `.proto` file, up to the end of its `syntax` statement.
+///
+/// The file's own location is left open for the statements which follow, until [`end`] is
+/// reached.
+pub(super) fn start(input: Span) -> IResult<Syntax> {
+    let (input, comments) = comment::collect(input, true)?;
+    input.extra.record_file_comments(comments);
+    // Unlike `protoc`, which assumes proto2 with a warning, a file must declare its syntax. This
+    // includes files with no declarations at all, which are most likely mistakes.
+    if input.fragment().is_empty() {
+        return fail(input, "file is empty; expected `syntax` statement");
+    }
+    input.extra.record_location_start(&input, Tag::Root);
+    context("`syntax` statement", syntax::parse)(input)
+}
+
+/// Parses the end of a `.proto` file, finishing its location.
+///
+/// The statements stop at anything which isn't a valid statement, so this points out the token
+/// responsible rather than failing later on the leftover input.
+pub(super) fn end(input: Span) -> IResult<()> {
+    if !input.fragment().is_empty() {
+        let (_, token) = token::any(input)?;
+        return fail(
+            input,
+            format!(
+                "expected top-level statement (e.g. \"message\"), found \"{}\"",
+                token.fragment()
+            ),
+        );
+    }
+    input.extra.record_location_end(&input);
+    Ok((input, ()))
+}
+
+/// Parses a top-level statement like [`statement`], along with the index of its location.
+pub(super) fn located_statement(input: Span) -> IResult<Option<(Statement, usize)>> {
+    let index = input.extra.location_count();
+    let (input, statement) = statement(input)?;
+    Ok((input, statement.map(|statement| (statement, index))))
+}
+
+/// Parses a top-level statement, returning `None` for an empty or skipped statement.
+fn statement(input: Span) -> IResult<Option<Statement>> {
+    let result = alt((
+        map(package::parse, |package| Some(Statement::Package(package))),
+        map(import::parse, |(path, kind)| {
+            Some(Statement::Import(path, kind))
+        }),
+        map(
+            option::parse_statement::<FileOptions>(OPTIONS_TAG),
+            |options| Some(Statement::Option(options)),
+        ),
+        map(message::parse(message::FILE_TAG), |message| {
+            Some(Statement::Message(message))
+        }),
+        map(r#enum::parse(r#enum::FILE_TAG), |r#enum| {
+            Some(Statement::Enum(r#enum))
+        }),
+        map(service::parse(service::FILE_TAG), |service| {
+            Some(Statement::Service(service))
+        }),
+        map(extend::parse(extend::FILE_TAG), |fields| {
+            Some(Statement::Extend(fields))
+        }),
+        map(end_of_declaration(';', false), |()| None),
+    ))(input);
+    match result {
+        Err(nom::Err::Error(error)) if input.extra.lenient() => {
+            map(unknown_statement, |()| None)(input).map_err(|_| nom::Err::Error(error))
+        }
+        result => result,
+    }
+}
+
+/// Skips a statement which isn't recognized, such as one added by a newer version of the
+/// language, up to the end of the statement or of its body.
+///
+/// Only statements beginning with an identifier and with balanced braces are skipped.
+fn unknown_statement(input: Span) -> IResult<()> {
+    let (mut rest, _) = identifier::parse(input)?;
+    let mut depth = 0;
+    loop {
+        let (next, _) = ws0(rest)?;
+        if depth == 0 && peek(symbol(';'))(next).is_ok() {
+            rest = end_of_declaration(';', false)(next)?.0;
+            break;
+        }
+        if depth == 1 && peek(symbol('}'))(next).is_ok() {
+            rest = end_of_declaration('}', false)(next)?.0;
+            break;
+        }
+        let (next, token) = token::any(next)?;
+        match *token.fragment() {
+            "{" => depth += 1,
+            "}" if depth > 0 => depth -= 1,
+            "}" => return fail_parser(rest),
+            _ => (),
+        }
+        rest = next;
+    }
+    input.extra.record_skipped(&input);
+    Ok((rest, ()))
+}
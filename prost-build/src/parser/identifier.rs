@@ -0,0 +1,42 @@
+use nom::bytes::complete::take_while;
+use nom::character::complete::{char, satisfy};
+use nom::combinator::{map, opt, recognize};
+use nom::error::context;
+use nom::multi::separated_list1;
+use nom::sequence::pair;
+
+use super::token::is_ident_char;
+use super::{IResult, Span};
+
+/// Parses a simple identifier, such as a message or field name.
+pub(super) fn parse(input: Span) -> IResult<String> {
+    context(
+        "identifier",
+        map(
+            recognize(pair(
+                satisfy(|c| c.is_ascii_alphabetic() || c == '_'),
+                take_while(is_ident_char),
+            )),
+            |ident: Span| ident.fragment().to_string(),
+        ),
+    )(input)
+}
+
+/// Parses a dot-separated identifier, such as a package name.
+pub(super) fn parse_full(input: Span) -> IResult<String> {
+    map(
+        recognize(separated_list1(char('.'), parse)),
+        |ident: Span| ident.fragment().to_string(),
+    )(input)
+}
+
+/// Parses a reference to a type, which may be fully-qualified with a leading `.`.
+pub(super) fn parse_type(input: Span) -> IResult<String> {
+    context(
+        "type name",
+        map(
+            recognize(pair(opt(char('.')), parse_full)),
+            |ident: Span| ident.fragment().to_string(),
+        ),
+    )(input)
+}
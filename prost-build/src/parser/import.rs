@@ -0,0 +1,203 @@
+use nom::branch::alt;
+use nom::combinator::{cut, map, map_res, opt, value};
+use nom::error::context;
+use nom::sequence::{pair, preceded, terminated};
+use prost_types::{FileDescriptorProto, FileDescriptorSet};
+
+use super::location::{end_of_declaration, locate, outer, Tag};
+use super::token::{keyword, ws0};
+use super::{literal, resolve, start_of, Error, Result, Warning};
+use super::{IResult, Span};
+
+/// `FileDescriptorProto.dependency`
+const TAG: Tag = Tag::Repeated(3);
+/// `FileDescriptorProto.public_dependency`
+const PUBLIC_TAG: Tag = Tag::Repeated(10);
+/// `FileDescriptorProto.weak_dependency`
+const WEAK_TAG: Tag = Tag::Repeated(11);
+
+/// The kind of an `import` statement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportKind {
+    /// A plain `import`.
+    Default,
+    /// An `import public`, whose definitions are also visible to files importing this one.
+    Public,
+    /// An `import weak`, which may be missing at runtime.
+    Weak,
+}
+
+/// Returns every import of `file`, in declaration order, along with its kind.
+pub fn imports(file: &FileDescriptorProto) -> Vec<(ImportKind, String)> {
+    file.dependency
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let i = i as i32;
+            let kind = if file.public_dependency.contains(&i) {
+                ImportKind::Public
+            } else if file.weak_dependency.contains(&i) {
+                ImportKind::Weak
+            } else {
+                ImportKind::Default
+            };
+            (kind, path.clone())
+        })
+        .collect()
+}
+
+/// Returns the names of the files in `descriptors` which the file `name` imports, directly or
+/// indirectly.
+///
+/// The files are listed in dependency order, with each file following the files it imports.
+/// Imports of files which aren't in `descriptors` are ignored, and an error is returned if the
+/// imports form a cycle.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::path::PathBuf;
+///
+/// let files: HashMap<_, _> = [
+///     ("a.proto", r#"syntax = "proto3"; import "b.proto";"#),
+///     ("b.proto", r#"syntax = "proto3"; import "c.proto";"#),
+///     ("c.proto", r#"syntax = "proto3";"#),
+/// ]
+/// .iter()
+/// .map(|(name, source)| (PathBuf::from(name), source.to_string()))
+/// .collect();
+/// let descriptors = prost_build::parser::parse(&files).unwrap();
+///
+/// let dependencies =
+///     prost_build::parser::transitive_dependencies(&descriptors, "a.proto").unwrap();
+/// assert_eq!(dependencies, ["c.proto", "b.proto"]);
+/// ```
+pub fn transitive_dependencies(descriptors: &FileDescriptorSet, name: &str) -> Result<Vec<String>> {
+    resolve::dependencies(&descriptors.file, name).map_err(Error::Resolution)
+}
+
+/// Replaces each import of `file` which doesn't name any of `names` exactly, but matches exactly
+/// one of them when case is ignored, with the matching name.
+///
+/// A warning is returned for each import replaced, since the import would only be found on a
+/// case-insensitive filesystem.
+pub(super) fn match_case_insensitively(
+    file: &mut FileDescriptorProto,
+    names: &[String],
+) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for index in 0..file.dependency.len() {
+        let dependency = &file.dependency[index];
+        if names.contains(dependency) {
+            continue;
+        }
+        let mut matches = names
+            .iter()
+            .filter(|name| name.eq_ignore_ascii_case(dependency));
+        if let (Some(name), None) = (matches.next(), matches.next()) {
+            let (line, column) = start_of(file, &[3, index as i32]).unwrap_or_default();
+            warnings.push(Warning {
+                file: file.name().to_owned(),
+                line,
+                column,
+                message: format!(
+                    "import \"{}\" matched \"{}\" ignoring case",
+                    dependency, name
+                ),
+            });
+            file.dependency[index] = name.clone();
+        }
+    }
+    warnings
+}
+
+/// Parses an `import "foo.proto";` statement, optionally qualified with `public` or `weak`.
+pub(super) fn parse(input: Span) -> IResult<(String, ImportKind)> {
+    locate(
+        TAG,
+        preceded(
+            keyword("import"),
+            cut(map(
+                pair(
+                    opt(preceded(ws0, kind)),
+                    terminated(
+                        preceded(
+                            ws0,
+                            context("import path", map_res(literal::string, String::from_utf8)),
+                        ),
+                        context("`;`", end_of_declaration(';', true)),
+                    ),
+                ),
+                |(kind, path)| (path, kind.unwrap_or(ImportKind::Default)),
+            )),
+        ),
+    )(input)
+}
+
+/// Parses the `public` or `weak` qualifier of an import.
+///
+/// These are recorded relative to the file, rather than within the import's location.
+fn kind(input: Span) -> IResult<ImportKind> {
+    outer(alt((
+        value(ImportKind::Public, locate(PUBLIC_TAG, keyword("public"))),
+        value(ImportKind::Weak, locate(WEAK_TAG, keyword("weak"))),
+    )))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::parser::{parse, parse_str};
+
+    #[test]
+    fn classifies_imports() {
+        let file = parse_str(
+            "test.proto",
+            r#"
+            syntax = "proto3";
+            import "a.proto";
+            import public "b.proto";
+            import weak "c.proto";
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            imports(&file),
+            [
+                (ImportKind::Default, "a.proto".to_owned()),
+                (ImportKind::Public, "b.proto".to_owned()),
+                (ImportKind::Weak, "c.proto".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_transitive_dependencies() {
+        let files = HashMap::from([
+            (
+                PathBuf::from("a.proto"),
+                r#"syntax = "proto3"; import "b.proto"; import "d.proto";"#.to_owned(),
+            ),
+            (
+                PathBuf::from("b.proto"),
+                r#"syntax = "proto3"; import public "c.proto";"#.to_owned(),
+            ),
+            (
+                PathBuf::from("c.proto"),
+                r#"syntax = "proto3"; import "d.proto";"#.to_owned(),
+            ),
+            (PathBuf::from("d.proto"), r#"syntax = "proto3";"#.to_owned()),
+            (PathBuf::from("e.proto"), r#"syntax = "proto3";"#.to_owned()),
+        ]);
+        let descriptors = parse(&files).unwrap();
+
+        let dependencies = |name| transitive_dependencies(&descriptors, name).unwrap();
+        assert_eq!(dependencies("a.proto"), ["d.proto", "c.proto", "b.proto"]);
+        assert_eq!(dependencies("b.proto"), ["d.proto", "c.proto"]);
+        assert!(dependencies("d.proto").is_empty());
+        assert!(dependencies("missing.proto").is_empty());
+    }
+}
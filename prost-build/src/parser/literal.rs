@@ -0,0 +1,169 @@
+use nom::branch::alt;
+use nom::bytes::complete::{take_while1, take_while_m_n};
+use nom::character::complete::{char, digit0, digit1, none_of, one_of, satisfy};
+use nom::combinator::{map, map_res, not, opt, peek, recognize};
+use nom::error::context;
+use nom::multi::fold_many0;
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+
+use super::token::{is_ident_char, ws0};
+use super::{IResult, Span};
+
+/// Parses an unsigned decimal, hexadecimal or octal integer literal.
+pub(super) fn int(input: Span) -> IResult<u64> {
+    context(
+        "integer",
+        terminated(
+            alt((
+                map_res(
+                    preceded(
+                        pair(char('0'), one_of("xX")),
+                        take_while1(|c: char| c.is_ascii_hexdigit()),
+                    ),
+                    |digits: Span| u64::from_str_radix(digits.fragment(), 16),
+                ),
+                map_res(
+                    preceded(char('0'), take_while1(|c: char| c.is_digit(8))),
+                    |digits: Span| u64::from_str_radix(digits.fragment(), 8),
+                ),
+                map_res(digit1, |digits: Span| digits.fragment().parse::<u64>()),
+            )),
+            not(peek(satisfy(|c| is_ident_char(c) || c == '.'))),
+        ),
+    )(input)
+}
+
+/// Parses a possibly negative integer literal which fits in an `i32`.
+pub(super) fn int32(input: Span) -> IResult<i32> {
+    map_res(
+        pair(opt(terminated(char('-'), ws0)), int),
+        |(sign, value)| {
+            let value = i64::try_from(value).map_err(|_| "integer out of range")?;
+            let value = if sign.is_some() { -value } else { value };
+            i32::try_from(value).map_err(|_| "integer out of range")
+        },
+    )(input)
+}
+
+/// Parses a floating point literal, such as `1.5`, `.5`, `1e10` or `2.5E-3`.
+///
+/// Integer literals without a fractional part or exponent are not accepted.
+pub(super) fn float(input: Span) -> IResult<f64> {
+    let exponent = || tuple((one_of("eE"), opt(one_of("+-")), digit1));
+    context(
+        "floating point number",
+        map_res(
+            terminated(
+                alt((
+                    recognize(tuple((digit1, char('.'), digit0, opt(exponent())))),
+                    recognize(tuple((char('.'), digit1, opt(exponent())))),
+                    recognize(pair(digit1, exponent())),
+                )),
+                not(peek(satisfy(is_ident_char))),
+            ),
+            |float: Span| float.fragment().parse::<f64>(),
+        ),
+    )(input)
+}
+
+/// Parses a string literal, returning the unescaped bytes.
+///
+/// As in C, adjacent string literals are concatenated.
+pub(super) fn string(input: Span) -> IResult<Vec<u8>> {
+    context(
+        "string",
+        map(
+            pair(single_string, many_strings),
+            |(mut first, rest): (Vec<u8>, Vec<u8>)| {
+                first.extend(rest);
+                first
+            },
+        ),
+    )(input)
+}
+
+fn many_strings(input: Span) -> IResult<Vec<u8>> {
+    fold_many0(preceded(ws0, single_string), Vec::new, |mut acc, s| {
+        acc.extend(s);
+        acc
+    })(input)
+}
+
+/// Parses a single string literal, without concatenating adjacent literals.
+pub(super) fn single_string(input: Span) -> IResult<Vec<u8>> {
+    alt((quoted('"'), quoted('\'')))(input)
+}
+
+fn quoted<'a>(quote: char) -> impl FnMut(Span<'a>) -> IResult<'a, Vec<u8>> {
+    move |input: Span<'a>| {
+        delimited(
+            char(quote),
+            fold_many0(
+                alt((
+                    escape,
+                    map(none_of(&[quote, '\\', '\n', '\0'][..]), |c: char| {
+                        let mut buf = [0; 4];
+                        c.encode_utf8(&mut buf).as_bytes().to_vec()
+                    }),
+                )),
+                Vec::new,
+                |mut acc, bytes| {
+                    acc.extend(bytes);
+                    acc
+                },
+            ),
+            char(quote),
+        )(input)
+    }
+}
+
+/// Parses an escape sequence within a string literal.
+fn escape(input: Span) -> IResult<Vec<u8>> {
+    preceded(
+        char('\\'),
+        alt((
+            map(one_of("abfnrtv\\?'\""), |c| {
+                vec![match c {
+                    'a' => 0x07,
+                    'b' => 0x08,
+                    'f' => 0x0c,
+                    'n' => b'\n',
+                    'r' => b'\r',
+                    't' => b'\t',
+                    'v' => 0x0b,
+                    c => c as u8,
+                }]
+            }),
+            map_res(
+                preceded(
+                    one_of("xX"),
+                    take_while_m_n(1, 2, |c: char| c.is_ascii_hexdigit()),
+                ),
+                |digits: Span| u8::from_str_radix(digits.fragment(), 16).map(|b| vec![b]),
+            ),
+            map_res(
+                take_while_m_n(1, 3, |c: char| c.is_digit(8)),
+                |digits: Span| u8::from_str_radix(digits.fragment(), 8).map(|b| vec![b]),
+            ),
+            map_res(
+                alt((
+                    preceded(
+                        char('u'),
+                        take_while_m_n(4, 4, |c: char| c.is_ascii_hexdigit()),
+                    ),
+                    preceded(
+                        char('U'),
+                        take_while_m_n(8, 8, |c: char| c.is_ascii_hexdigit()),
+                    ),
+                )),
+                |digits: Span| {
+                    u32::from_str_radix(digits.fragment(), 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .map(|c| c.to_string().into_bytes())
+                        .ok_or("invalid unicode escape")
+                },
+            ),
+        )),
+    )(input)
+}
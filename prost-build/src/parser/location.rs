@@ -0,0 +1,373 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::mem;
+use std::ops::Range;
+
+use nom::character::complete::char;
+use prost_types::field_descriptor_proto::Type;
+use prost_types::source_code_info::Location;
+use prost_types::SourceCodeInfo;
+
+use super::comment::{self, Comments};
+use super::syntax::Syntax;
+use super::token::ws0;
+use super::{IResult, Span};
+
+/// Identifies where a parsed element is stored within its enclosing descriptor, and therefore
+/// how its `SourceCodeInfo` location path is derived from the enclosing element's path.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Tag {
+    /// The file itself, which has an empty path.
+    Root,
+    /// A singular field of the enclosing descriptor, e.g. `FileDescriptorProto.package`.
+    Field(i32),
+    /// An element of a repeated field of the enclosing descriptor, e.g.
+    /// `FileDescriptorProto.message_type`.
+    Repeated(i32),
+    /// An element of the repeated field whose location encloses this element, e.g. one range of
+    /// a `reserved` statement.
+    Index,
+}
+
+/// A position in the source text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct Position {
+    pub(super) offset: usize,
+    /// The zero-based line.
+    pub(super) line: i32,
+    /// The zero-based column, counted as `protoc` does.
+    pub(super) column: i32,
+}
+
+/// The column width of a tab, as in `protoc`'s tokenizer.
+const TAB_WIDTH: i32 = 8;
+
+impl Position {
+    pub(super) fn of(input: &Span) -> Position {
+        // As with `protoc`, columns count bytes rather than characters, and a tab advances the
+        // column to the next multiple of the tab width.
+        let column = input.get_line_beginning()[..input.get_column() - 1]
+            .iter()
+            .fold(0, |column, &byte| match byte {
+                b'\t' => column + TAB_WIDTH - column % TAB_WIDTH,
+                _ => column + 1,
+            });
+        Position {
+            offset: input.location_offset(),
+            line: input.location_line() as i32 - 1,
+            column,
+        }
+    }
+}
+
+/// Parse state shared by every parser through the input [`Span`].
+///
+/// The state records `SourceCodeInfo` locations as elements are parsed, and tracks the comments
+/// which will be attached to the next declaration. Since parsers may backtrack, every change to
+/// the state made by a failed parser is rolled back by [`locate`].
+#[derive(Debug, Default)]
+pub(super) struct State {
+    inner: RefCell<Inner>,
+    syntax: Cell<Syntax>,
+    lenient: Cell<bool>,
+    /// The start of each unrecognized statement skipped in lenient mode.
+    skipped: RefCell<Vec<Position>>,
+    /// Additional names for scalar types, registered with [`Parser::scalar_alias`].
+    ///
+    /// [`Parser::scalar_alias`]: super::Parser::scalar_alias
+    scalar_aliases: HashMap<String, Type>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    locations: Vec<Location>,
+    /// The byte range of each location in `locations`.
+    offsets: Vec<Range<usize>>,
+    /// The locations which are still being parsed, as (location index, start offset) pairs.
+    open: Vec<(usize, usize)>,
+    /// The stack of paths that `Tag`s are relative to, innermost last.
+    scopes: Vec<Vec<i32>>,
+    /// The next index to assign for each repeated field, keyed by the field's path.
+    indices: HashMap<Vec<i32>, i32>,
+    /// The keys of `indices` which were incremented, in order, so that they can be rolled back.
+    index_log: Vec<Vec<i32>>,
+    /// Comments which will be attached to the next declaration.
+    upcoming: Comments,
+    /// The end of the last declaration delimiter.
+    last_end: Option<Position>,
+}
+
+/// A snapshot of the [`State`] taken before parsing an element.
+pub(super) struct Checkpoint {
+    locations: usize,
+    open: usize,
+    scopes: usize,
+    index_log: usize,
+    upcoming: Comments,
+    last_end: Option<Position>,
+}
+
+impl State {
+    /// Returns the syntax of the file being parsed.
+    pub(super) fn syntax(&self) -> Syntax {
+        self.syntax.get()
+    }
+
+    pub(super) fn set_syntax(&self, syntax: Syntax) {
+        self.syntax.set(syntax);
+    }
+
+    /// Returns whether unrecognized top-level statements should be skipped rather than rejected.
+    pub(super) fn lenient(&self) -> bool {
+        self.lenient.get()
+    }
+
+    pub(super) fn set_lenient(&self, lenient: bool) {
+        self.lenient.set(lenient);
+    }
+
+    /// Returns the scalar type which `name` is an alias for, if any.
+    pub(super) fn scalar_alias(&self, name: &str) -> Option<Type> {
+        self.scalar_aliases.get(name).copied()
+    }
+
+    pub(super) fn set_scalar_aliases(&mut self, aliases: HashMap<String, Type>) {
+        self.scalar_aliases = aliases;
+    }
+
+    /// Records that the unrecognized statement starting at `input` was skipped.
+    pub(super) fn record_skipped(&self, input: &Span) {
+        self.skipped.borrow_mut().push(Position::of(input));
+    }
+
+    /// Returns the start of each statement skipped so far.
+    pub(super) fn skipped(&self) -> Vec<Position> {
+        self.skipped.borrow().clone()
+    }
+
+    /// Returns the number of locations recorded so far, which is the index of the next one.
+    pub(super) fn location_count(&self) -> usize {
+        self.inner.borrow().locations.len()
+    }
+
+    /// Returns a copy of the location with the given index.
+    pub(super) fn location(&self, index: usize) -> Location {
+        self.inner.borrow().locations[index].clone()
+    }
+
+    /// Starts recording the location of an element beginning at `input`.
+    ///
+    /// The returned checkpoint can be used to roll back the state if the element fails to parse.
+    pub(super) fn record_location_start(&self, input: &Span, tag: Tag) -> Checkpoint {
+        let mut inner = self.inner.borrow_mut();
+        let checkpoint = Checkpoint {
+            locations: inner.locations.len(),
+            open: inner.open.len(),
+            scopes: inner.scopes.len(),
+            index_log: inner.index_log.len(),
+            upcoming: inner.upcoming.clone(),
+            last_end: inner.last_end,
+        };
+
+        let mut path = inner.scopes.last().cloned().unwrap_or_default();
+        match tag {
+            Tag::Root => (),
+            Tag::Field(number) => path.push(number),
+            Tag::Repeated(number) => {
+                path.push(number);
+                let index = inner.next_index(path.clone());
+                path.push(index);
+            }
+            Tag::Index => {
+                let index = inner.next_index(path.clone());
+                path.push(index);
+            }
+        }
+
+        let start = Position::of(input);
+        inner.locations.push(Location {
+            path: path.clone(),
+            span: vec![start.line, start.column],
+            ..Location::default()
+        });
+        inner.offsets.push(start.offset..start.offset);
+        let index = inner.locations.len() - 1;
+        inner.open.push((index, start.offset));
+        inner.scopes.push(path);
+        checkpoint
+    }
+
+    /// Assigns the next index of the repeated field `tag` of the current scope to an element
+    /// which has no location, such as a synthetic map entry message.
+    pub(super) fn reserve_index(&self, tag: Tag) {
+        let mut inner = self.inner.borrow_mut();
+        let mut path = inner.scopes.last().cloned().unwrap_or_default();
+        match tag {
+            Tag::Repeated(number) => path.push(number),
+            Tag::Root | Tag::Field(_) | Tag::Index => unreachable!("tag isn't a repeated field"),
+        }
+        inner.next_index(path);
+    }
+
+    /// Finishes recording the innermost open location, which ends at `input`.
+    ///
+    /// If the element ended with a declaration delimiter, the location ends at the delimiter
+    /// rather than after the comments and whitespace following it.
+    pub(super) fn record_location_end(&self, input: &Span) {
+        let mut inner = self.inner.borrow_mut();
+        let (index, start) = inner.open.pop().expect("no open location");
+        inner.scopes.pop();
+
+        let end = match inner.last_end {
+            Some(end) if end.offset >= start && end.offset <= input.location_offset() => end,
+            _ => Position::of(input),
+        };
+        inner.offsets[index].end = end.offset;
+        let span = &mut inner.locations[index].span;
+        if span[0] != end.line {
+            span.push(end.line);
+        }
+        span.push(end.column);
+    }
+
+    /// Restores the state to a checkpoint, discarding everything recorded since.
+    pub(super) fn rollback(&self, checkpoint: Checkpoint) {
+        let mut inner = self.inner.borrow_mut();
+        inner.locations.truncate(checkpoint.locations);
+        inner.offsets.truncate(checkpoint.locations);
+        inner.open.truncate(checkpoint.open);
+        inner.scopes.truncate(checkpoint.scopes);
+        while inner.index_log.len() > checkpoint.index_log {
+            let key = inner.index_log.pop().unwrap();
+            *inner.indices.get_mut(&key).unwrap() -= 1;
+        }
+        inner.upcoming = checkpoint.upcoming;
+        inner.last_end = checkpoint.last_end;
+    }
+
+    /// Records the comments preceding the first declaration of the file.
+    pub(super) fn record_file_comments(&self, comments: Comments) {
+        self.inner.borrow_mut().upcoming = comments;
+    }
+
+    /// Records the end of a declaration delimiter, along with the comments following it.
+    ///
+    /// This mirrors `protoc`: the comments collected before the declaration and the trailing
+    /// comment after its delimiter are attached to the innermost open location if `attach` is
+    /// set, and the comments preceding the next declaration are saved for later.
+    fn end_of_declaration(&self, end: Position, comments: Comments, delimiter: char, attach: bool) {
+        let mut inner = self.inner.borrow_mut();
+        inner.last_end = Some(end);
+
+        let leading = mem::replace(&mut inner.upcoming.leading, comments.leading);
+        if attach {
+            let detached = mem::replace(&mut inner.upcoming.detached, comments.detached);
+            if let Some(&(index, _)) = inner.open.last() {
+                let location = &mut inner.locations[index];
+                location.leading_comments = leading;
+                location.trailing_comments = comments.trailing;
+                location.leading_detached_comments = detached;
+            }
+        } else if delimiter == '}' {
+            // The scope is finished, so any pending detached comments have nothing to attach to.
+            inner.upcoming.detached = comments.detached;
+        } else {
+            inner.upcoming.detached.extend(comments.detached);
+        }
+    }
+
+    /// Makes the scope enclosing the current scope the innermost one.
+    fn enter_outer_scope(&self) {
+        let mut inner = self.inner.borrow_mut();
+        let len = inner.scopes.len();
+        let outer = if len >= 2 {
+            inner.scopes[len - 2].clone()
+        } else {
+            Vec::new()
+        };
+        inner.scopes.push(outer);
+    }
+
+    fn exit_scope(&self) {
+        self.inner.borrow_mut().scopes.pop();
+    }
+
+    /// Consumes the state, returning the recorded locations along with their byte ranges.
+    pub(super) fn into_locations(self) -> (SourceCodeInfo, Vec<Range<usize>>) {
+        let inner = self.inner.into_inner();
+        let info = SourceCodeInfo {
+            location: inner.locations,
+        };
+        (info, inner.offsets)
+    }
+}
+
+impl Inner {
+    fn next_index(&mut self, key: Vec<i32>) -> i32 {
+        let next = self.indices.entry(key.clone()).or_insert(0);
+        let index = *next;
+        *next += 1;
+        self.index_log.push(key);
+        index
+    }
+}
+
+/// Records the `SourceCodeInfo` location of the element parsed by `parser`.
+///
+/// Elements nested within `parser` are located relative to this element's path.
+pub(super) fn locate<'a, O, F>(tag: Tag, mut parser: F) -> impl FnMut(Span<'a>) -> IResult<'a, O>
+where
+    F: FnMut(Span<'a>) -> IResult<'a, O>,
+{
+    move |input: Span<'a>| {
+        let state = input.extra;
+        let checkpoint = state.record_location_start(&input, tag);
+        match parser(input) {
+            Ok((rest, output)) => {
+                state.record_location_end(&rest);
+                Ok((rest, output))
+            }
+            Err(error) => {
+                state.rollback(checkpoint);
+                Err(error)
+            }
+        }
+    }
+}
+
+/// Runs `parser` with locations relative to the scope enclosing the current one.
+///
+/// This is used for elements which are declared inside another element, but are stored in the
+/// enclosing descriptor, such as the fields of a `oneof`.
+pub(super) fn outer<'a, O, F>(mut parser: F) -> impl FnMut(Span<'a>) -> IResult<'a, O>
+where
+    F: FnMut(Span<'a>) -> IResult<'a, O>,
+{
+    move |input: Span<'a>| {
+        let state = input.extra;
+        state.enter_outer_scope();
+        let result = parser(input);
+        state.exit_scope();
+        result
+    }
+}
+
+/// Parses the delimiter ending a declaration or block, and the comments following it.
+///
+/// If `attach` is set, the comments surrounding the declaration are attached to the innermost
+/// location being recorded.
+pub(super) fn end_of_declaration<'a>(
+    delimiter: char,
+    attach: bool,
+) -> impl FnMut(Span<'a>) -> IResult<'a, ()> {
+    move |input: Span<'a>| {
+        let (input, _) = ws0(input)?;
+        let (input, _) = char(delimiter)(input)?;
+        let end = Position::of(&input);
+        let (input, comments) = comment::collect(input, false)?;
+        input
+            .extra
+            .end_of_declaration(end, comments, delimiter, attach);
+        Ok((input, ()))
+    }
+}
@@ -0,0 +1,522 @@
+use std::collections::HashMap;
+
+use prost_types::source_code_info::Location;
+use prost_types::{
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+    FileDescriptorSet,
+};
+
+/// A message or enum definition referred to by a field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TypeDescriptor<'a> {
+    Message(&'a DescriptorProto),
+    Enum(&'a EnumDescriptorProto),
+}
+
+/// Returns the definition of the message or enum type of a resolved `field`.
+///
+/// Returns `None` if the field has a scalar type, or its type isn't defined by any file in
+/// `descriptors`.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::path::PathBuf;
+///
+/// use prost_build::parser::{field_type, TypeDescriptor};
+///
+/// let mut files = HashMap::new();
+/// files.insert(
+///     PathBuf::from("shapes.proto"),
+///     r#"syntax = "proto3"; message Point {} message Line { Point start = 1; }"#.to_string(),
+/// );
+/// let descriptors = prost_build::parser::parse(&files).unwrap();
+///
+/// let field = &descriptors.file[0].message_type[1].field[0];
+/// match field_type(&descriptors, field) {
+///     Some(TypeDescriptor::Message(message)) => assert_eq!(message.name(), "Point"),
+///     _ => panic!("expected a message"),
+/// }
+/// ```
+pub fn field_type<'a>(
+    descriptors: &'a FileDescriptorSet,
+    field: &FieldDescriptorProto,
+) -> Option<TypeDescriptor<'a>> {
+    let full_name = field.type_name.as_deref()?.strip_prefix('.')?;
+    descriptors.file.iter().find_map(|file| {
+        let name = relative_name(file, full_name)?;
+        find(&file.message_type, &file.enum_type, name)
+    })
+}
+
+/// Returns the name of the file in `descriptors` which defines the element with the given
+/// fully-qualified name, such as `.foo.Bar`.
+///
+/// The element may be a message, enum, service, field, `oneof`, enum value or method, as with
+/// [`element_location`]. Returns `None` if no file defines it.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::path::PathBuf;
+///
+/// let mut files = HashMap::new();
+/// files.insert(
+///     PathBuf::from("point.proto"),
+///     r#"syntax = "proto3"; package shapes; message Point {}"#.to_string(),
+/// );
+/// files.insert(
+///     PathBuf::from("line.proto"),
+///     r#"syntax = "proto3"; package shapes; import "point.proto";
+///     message Line { Point start = 1; }"#
+///         .to_string(),
+/// );
+/// let descriptors = prost_build::parser::parse(&files).unwrap();
+///
+/// let file = prost_build::parser::defining_file(&descriptors, ".shapes.Point");
+/// assert_eq!(file, Some("point.proto"));
+/// ```
+pub fn defining_file<'a>(descriptors: &'a FileDescriptorSet, name: &str) -> Option<&'a str> {
+    let full_name = name.strip_prefix('.').unwrap_or(name);
+    descriptors
+        .file
+        .iter()
+        .find(|file| {
+            relative_name(file, full_name).map_or(false, |name| element_path(file, name).is_some())
+        })
+        .map(|file| file.name())
+}
+
+/// Strips the package of `file` from `full_name`, which has no leading `.`, returning `None` if
+/// the name is outside the package.
+pub(super) fn relative_name<'a>(file: &FileDescriptorProto, full_name: &'a str) -> Option<&'a str> {
+    match file.package() {
+        "" => Some(full_name),
+        package => full_name.strip_prefix(package)?.strip_prefix('.'),
+    }
+}
+
+/// Finds the type with the given relative name among `messages` and `enums` and their nested
+/// types.
+fn find<'a>(
+    messages: &'a [DescriptorProto],
+    enums: &'a [EnumDescriptorProto],
+    name: &str,
+) -> Option<TypeDescriptor<'a>> {
+    match name.split_once('.') {
+        Some((first, rest)) => {
+            let message = messages.iter().find(|message| message.name() == first)?;
+            find(&message.nested_type, &message.enum_type, rest)
+        }
+        None => messages
+            .iter()
+            .find(|message| message.name() == name)
+            .map(TypeDescriptor::Message)
+            .or_else(|| {
+                enums
+                    .iter()
+                    .find(|r#enum| r#enum.name() == name)
+                    .map(TypeDescriptor::Enum)
+            }),
+    }
+}
+
+/// Returns every extension field declared in `descriptors`, along with the fully-qualified name
+/// of the message it extends.
+///
+/// Extensions are listed file by file, with those declared at the top level of a file before
+/// those nested within its messages.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::path::PathBuf;
+///
+/// let mut files = HashMap::new();
+/// files.insert(
+///     PathBuf::from("options.proto"),
+///     r#"syntax = "proto2"; package foo; message Bar { extensions 100 to 199; }
+///     extend Bar { optional int32 baz = 100; }"#
+///         .to_string(),
+/// );
+/// let descriptors = prost_build::parser::parse(&files).unwrap();
+///
+/// let extensions = prost_build::parser::extensions(&descriptors);
+/// assert_eq!(extensions.len(), 1);
+/// assert_eq!(extensions[0].0, ".foo.Bar");
+/// assert_eq!(extensions[0].1.name(), "baz");
+/// ```
+pub fn extensions(descriptors: &FileDescriptorSet) -> Vec<(&str, &FieldDescriptorProto)> {
+    let mut extensions = Vec::new();
+    for file in &descriptors.file {
+        extensions.extend(file.extension.iter().map(extendee));
+        let mut messages: Vec<&DescriptorProto> = file.message_type.iter().rev().collect();
+        while let Some(message) = messages.pop() {
+            extensions.extend(message.extension.iter().map(extendee));
+            messages.extend(message.nested_type.iter().rev());
+        }
+    }
+    extensions
+}
+
+fn extendee(field: &FieldDescriptorProto) -> (&str, &FieldDescriptorProto) {
+    (field.extendee(), field)
+}
+
+/// Returns the leading comment of each field of `file` which has one, keyed by the field's
+/// fully-qualified name, such as `.foo.Bar.baz`.
+///
+/// Fields of nested messages are included. Returns an empty map if the file has no source code
+/// info.
+///
+/// ```
+/// let file = prost_build::parser::parse_str(
+///     "greeting.proto",
+///     "syntax = \"proto3\";\npackage hello;\nmessage Greeting {\n  // The text.\n  string text = 1;\n}\n",
+/// )
+/// .unwrap();
+///
+/// let comments = prost_build::parser::field_comments(&file);
+/// assert_eq!(comments[".hello.Greeting.text"], " The text.\n");
+/// ```
+pub fn field_comments(file: &FileDescriptorProto) -> HashMap<String, String> {
+    let comments: HashMap<&[i32], &str> = match &file.source_code_info {
+        Some(info) => info
+            .location
+            .iter()
+            .filter_map(|location| {
+                Some((&location.path[..], location.leading_comments.as_deref()?))
+            })
+            .collect(),
+        None => return HashMap::new(),
+    };
+
+    let mut field_comments = HashMap::new();
+    let mut messages: Vec<_> = file
+        .message_type
+        .iter()
+        .enumerate()
+        .map(|(i, message)| {
+            let name = match file.package() {
+                "" => format!(".{}", message.name()),
+                package => format!(".{}.{}", package, message.name()),
+            };
+            (name, vec![4, i as i32], message)
+        })
+        .collect();
+    while let Some((name, path, message)) = messages.pop() {
+        for (i, field) in message.field.iter().enumerate() {
+            if let Some(comment) = comments.get(&[&path[..], &[2, i as i32]].concat()[..]) {
+                field_comments.insert(format!("{}.{}", name, field.name()), comment.to_string());
+            }
+        }
+        for (i, nested) in message.nested_type.iter().enumerate() {
+            messages.push((
+                format!("{}.{}", name, nested.name()),
+                [&path[..], &[3, i as i32]].concat(),
+                nested,
+            ));
+        }
+    }
+    field_comments
+}
+
+/// Returns the source location of the element of `file` with the given name.
+///
+/// The name is relative to the file's package, such as `Outer.Inner.field` or `Service.Method`,
+/// and may name a message, enum, service, field, `oneof`, enum value or method. A
+/// fully-qualified name with a leading `.` is also accepted.
+///
+/// Returns `None` if there is no such element, or the file has no source code info.
+///
+/// ```
+/// let file = prost_build::parser::parse_str(
+///     "greeting.proto",
+///     "syntax = \"proto3\";\nmessage Greeting {\n  // The text.\n  string text = 1;\n}\n",
+/// )
+/// .unwrap();
+///
+/// let location = prost_build::parser::element_location(&file, "Greeting.text").unwrap();
+/// assert_eq!(location.span, [3, 2, 18]);
+/// assert_eq!(location.leading_comments(), " The text.\n");
+/// ```
+pub fn element_location<'a>(file: &'a FileDescriptorProto, name: &str) -> Option<&'a Location> {
+    let name = match name.strip_prefix('.') {
+        Some(full_name) => relative_name(file, full_name)?,
+        None => name,
+    };
+    let path = element_path(file, name)?;
+    file.source_code_info
+        .as_ref()?
+        .location
+        .iter()
+        .find(|location| location.path == path)
+}
+
+/// Returns the `SourceCodeInfo` path of the element of `file` with the given relative name.
+fn element_path(file: &FileDescriptorProto, name: &str) -> Option<Vec<i32>> {
+    let (first, rest) = match name.split_once('.') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (name, None),
+    };
+    if let Some(i) = position(&file.message_type, first, DescriptorProto::name) {
+        let mut path = vec![4, i];
+        let message = &file.message_type[i as usize];
+        return match rest {
+            Some(rest) => message_path(message, rest, &mut path).then(|| path),
+            None => Some(path),
+        };
+    }
+    if let Some(i) = position(&file.enum_type, first, EnumDescriptorProto::name) {
+        let values = &file.enum_type[i as usize].value;
+        return match rest {
+            Some(rest) => position(values, rest, |value| value.name()).map(|j| vec![5, i, 2, j]),
+            None => Some(vec![5, i]),
+        };
+    }
+    let i = position(&file.service, first, |service| service.name())?;
+    let methods = &file.service[i as usize].method;
+    match rest {
+        Some(rest) => position(methods, rest, |method| method.name()).map(|j| vec![6, i, 2, j]),
+        None => Some(vec![6, i]),
+    }
+}
+
+/// Extends `path`, the path of `message`, to the path of the element with the given name
+/// relative to the message, returning whether the element exists.
+fn message_path(message: &DescriptorProto, name: &str, path: &mut Vec<i32>) -> bool {
+    let (first, rest) = match name.split_once('.') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (name, None),
+    };
+    if let Some(i) = position(&message.nested_type, first, DescriptorProto::name) {
+        path.extend([3, i]);
+        return match rest {
+            Some(rest) => message_path(&message.nested_type[i as usize], rest, path),
+            None => true,
+        };
+    }
+    if let Some(i) = position(&message.enum_type, first, EnumDescriptorProto::name) {
+        path.extend([4, i]);
+        let values = &message.enum_type[i as usize].value;
+        return match rest.map(|rest| position(values, rest, |value| value.name())) {
+            Some(Some(j)) => {
+                path.extend([2, j]);
+                true
+            }
+            Some(None) => false,
+            None => true,
+        };
+    }
+    if rest.is_some() {
+        return false;
+    }
+    if let Some(i) = position(&message.field, first, FieldDescriptorProto::name) {
+        path.extend([2, i]);
+        true
+    } else if let Some(i) = position(&message.oneof_decl, first, |oneof| oneof.name()) {
+        path.extend([8, i]);
+        true
+    } else {
+        false
+    }
+}
+
+/// Returns the index of the element of `elements` with the given name.
+fn position<T>(elements: &[T], name: &str, element_name: impl Fn(&T) -> &str) -> Option<i32> {
+    elements
+        .iter()
+        .position(|element| element_name(element) == name)
+        .map(|i| i as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::parser::{parse, parse_str};
+
+    #[test]
+    fn finds_field_types() {
+        let files = HashMap::from([
+            (
+                PathBuf::from("a.proto"),
+                r#"
+                syntax = "proto3";
+                package foo;
+                import "b.proto";
+                message A {
+                    foo.bar.B b = 1;
+                    foo.bar.B.Kind kind = 2;
+                    int32 count = 3;
+                }
+                "#
+                .to_owned(),
+            ),
+            (
+                PathBuf::from("b.proto"),
+                r#"
+                syntax = "proto3";
+                package foo.bar;
+                message B {
+                    enum Kind { KIND_UNSPECIFIED = 0; }
+                }
+                "#
+                .to_owned(),
+            ),
+        ]);
+        let descriptors = parse(&files).unwrap();
+        let b = &descriptors.file[0].message_type[0];
+        let fields = &descriptors.file[1].message_type[0].field;
+
+        assert_eq!(
+            field_type(&descriptors, &fields[0]),
+            Some(TypeDescriptor::Message(b))
+        );
+        assert_eq!(
+            field_type(&descriptors, &fields[1]),
+            Some(TypeDescriptor::Enum(&b.enum_type[0]))
+        );
+        assert_eq!(field_type(&descriptors, &fields[2]), None);
+    }
+
+    #[test]
+    fn finds_element_locations() {
+        let file = parse_str(
+            "test.proto",
+            r#"syntax = "proto3";
+package foo;
+message Outer {
+  message Inner {
+    // The value.
+    int32 value = 1;
+  }
+  enum Kind { KIND_UNSPECIFIED = 0; }
+  oneof choice { string text = 2; }
+}
+service Service {
+  rpc Get(Outer) returns (Outer);
+}
+"#,
+        )
+        .unwrap();
+
+        let span = |name| element_location(&file, name).map(|location| location.span.clone());
+        let value = element_location(&file, "Outer.Inner.value").unwrap();
+        assert_eq!(value.span, [5, 4, 20]);
+        assert_eq!(value.leading_comments(), " The value.\n");
+        assert_eq!(span(".foo.Outer.Inner.value"), Some(vec![5, 4, 20]));
+        assert_eq!(span("Outer.Kind.KIND_UNSPECIFIED"), Some(vec![7, 14, 35]));
+        assert_eq!(span("Outer.choice"), Some(vec![8, 2, 35]));
+        assert_eq!(span("Outer.text"), Some(vec![8, 17, 33]));
+        assert_eq!(span("Service.Get"), Some(vec![11, 2, 33]));
+        assert_eq!(span("Outer.missing"), None);
+        assert_eq!(span(".bar.Outer"), None);
+    }
+
+    #[test]
+    fn collects_field_comments() {
+        let file = parse_str(
+            "test.proto",
+            r#"syntax = "proto3";
+package foo;
+message Outer {
+  // The count.
+  int32 count = 1;
+  int32 undocumented = 2;
+  message Inner {
+    /* The value. */
+    string value = 1;
+  }
+}
+"#,
+        )
+        .unwrap();
+
+        let comments = field_comments(&file);
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[".foo.Outer.count"], " The count.\n");
+        assert_eq!(comments[".foo.Outer.Inner.value"], " The value. ");
+        assert!(!comments.contains_key(".foo.Outer.undocumented"));
+    }
+
+    #[test]
+    fn finds_defining_files() {
+        let files = HashMap::from([
+            (
+                PathBuf::from("a.proto"),
+                r#"syntax = "proto3"; package foo; import "b.proto"; message A { B b = 1; }"#
+                    .to_owned(),
+            ),
+            (
+                PathBuf::from("b.proto"),
+                r#"syntax = "proto3"; package foo; message B { enum Kind { KIND_UNSPECIFIED = 0; } }"#
+                    .to_owned(),
+            ),
+            (
+                PathBuf::from("c.proto"),
+                r#"syntax = "proto3"; message M {} service C { rpc Get(M) returns (M); }"#.to_owned(),
+            ),
+        ]);
+        let descriptors = parse(&files).unwrap();
+
+        assert_eq!(defining_file(&descriptors, ".foo.A"), Some("a.proto"));
+        assert_eq!(defining_file(&descriptors, ".foo.A.b"), Some("a.proto"));
+        assert_eq!(defining_file(&descriptors, ".foo.B.Kind"), Some("b.proto"));
+        assert_eq!(defining_file(&descriptors, ".C.Get"), Some("c.proto"));
+        assert_eq!(defining_file(&descriptors, ".foo.C"), None);
+        assert_eq!(defining_file(&descriptors, ".foo.Missing"), None);
+    }
+
+    #[test]
+    fn lists_extensions() {
+        let files = HashMap::from([(
+            PathBuf::from("test.proto"),
+            r#"syntax = "proto2";
+package foo;
+message A { extensions 100 to 199; }
+message B {
+  extensions 100 to max;
+  extend A { optional B b = 101; }
+}
+// Extends A.
+extend A {
+  optional int32 count = 100;
+}
+extend B { repeated string names = 100; }
+"#
+            .to_owned(),
+        )]);
+        let descriptors = parse(&files).unwrap();
+
+        let extensions: Vec<_> = extensions(&descriptors)
+            .into_iter()
+            .map(|(extendee, field)| (extendee, field.name(), field.type_name()))
+            .collect();
+        assert_eq!(
+            extensions,
+            [
+                (".foo.A", "count", ""),
+                (".foo.B", "names", ""),
+                (".foo.A", "b", ".foo.B"),
+            ]
+        );
+
+        let file = &descriptors.file[0];
+        let span = |path: &[i32]| {
+            file.source_code_info
+                .as_ref()
+                .unwrap()
+                .location
+                .iter()
+                .filter(|location| location.path == path)
+                .map(|location| location.span.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(span(&[7]), [vec![8, 0, 10, 1], vec![11, 0, 41]]);
+        assert_eq!(span(&[7, 0]), [vec![9, 2, 29]]);
+        assert_eq!(span(&[7, 1]), [vec![11, 11, 39]]);
+        assert_eq!(span(&[4, 1, 6]), [vec![5, 2, 34]]);
+        assert_eq!(span(&[4, 1, 6, 0]), [vec![5, 13, 32]]);
+        assert_eq!(span(&[4, 1, 2]), Vec::<Vec<i32>>::new());
+    }
+}
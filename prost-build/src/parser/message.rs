@@ -0,0 +1,206 @@
+use std::collections::HashSet;
+
+use nom::branch::alt;
+use nom::combinator::{cut, map};
+use nom::error::context;
+use nom::multi::{many0, separated_list1};
+use nom::sequence::{preceded, terminated};
+use prost_types::descriptor_proto::{ExtensionRange, ReservedRange};
+use prost_types::{
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, MessageOptions,
+    OneofDescriptorProto,
+};
+
+use super::error::fail;
+use super::location::{end_of_declaration, locate, Tag};
+use super::r#enum;
+use super::reserved::{self, Reserved, MAX_FIELD_NUMBER};
+use super::token::{keyword, symbol, ws0};
+use super::{extend, field, identifier, oneof, option, IResult, Span};
+
+/// `FileDescriptorProto.message_type`
+pub(super) const FILE_TAG: Tag = Tag::Repeated(4);
+/// `DescriptorProto.nested_type`
+pub(super) const NESTED_TAG: Tag = Tag::Repeated(3);
+/// `DescriptorProto.options`
+const OPTIONS_TAG: Tag = Tag::Field(7);
+/// `DescriptorProto.extension_range`
+const EXTENSION_RANGE_TAG: Tag = Tag::Field(5);
+
+enum Statement<'a> {
+    Option(MessageOptions),
+    Message(DescriptorProto),
+    Enum(EnumDescriptorProto),
+    Oneof(OneofDescriptorProto, Vec<FieldDescriptorProto>),
+    Reserved(Reserved),
+    Extensions(Vec<ExtensionRange>),
+    Extend(Vec<FieldDescriptorProto>),
+    /// A field, along with the input it was parsed from and its map entry or group message, if
+    /// any.
+    Field(Span<'a>, FieldDescriptorProto, Option<Box<DescriptorProto>>),
+    Empty,
+}
+
+/// Parses a `message` definition, which is stored in the enclosing descriptor's field `tag`.
+pub(super) fn parse<'a>(tag: Tag) -> impl FnMut(Span<'a>) -> IResult<'a, DescriptorProto> {
+    locate(
+        tag,
+        preceded(
+            keyword("message"),
+            cut(|input| {
+                let (input, name) = preceded(ws0, identifier::parse)(input)?;
+                body(name)(input)
+            }),
+        ),
+    )
+}
+
+/// Parses the body of a message with the given name, from its opening brace to its closing
+/// brace.
+///
+/// Nested elements are located relative to the innermost location being recorded.
+pub(super) fn body<'a>(name: String) -> impl FnMut(Span<'a>) -> IResult<'a, DescriptorProto> {
+    move |input| {
+        let (input, _) = context("`{`", end_of_declaration('{', true))(input)?;
+        let (input, statements) = many0(statement)(input)?;
+        let (input, _) = context("`}`", end_of_declaration('}', false))(input)?;
+
+        let mut message = DescriptorProto {
+            name: Some(name.clone()),
+            ..DescriptorProto::default()
+        };
+        let mut map_names = HashSet::new();
+        for statement in statements {
+            match statement {
+                Statement::Option(options) => option::merge(&mut message.options, options),
+                Statement::Message(nested) => message.nested_type.push(nested),
+                Statement::Enum(nested) => message.enum_type.push(nested),
+                Statement::Oneof(oneof, fields) => {
+                    let index = message.oneof_decl.len() as i32;
+                    message.oneof_decl.push(oneof);
+                    message
+                        .field
+                        .extend(fields.into_iter().map(|field| FieldDescriptorProto {
+                            oneof_index: Some(index),
+                            ..field
+                        }));
+                }
+                Statement::Reserved(Reserved::Ranges(ranges)) => {
+                    message
+                        .reserved_range
+                        .extend(ranges.into_iter().map(|(start, end)| ReservedRange {
+                            start: Some(start),
+                            end: Some(end + 1),
+                        }))
+                }
+                Statement::Reserved(Reserved::Names(names)) => message.reserved_name.extend(names),
+                Statement::Extensions(ranges) => message.extension_range.extend(ranges),
+                Statement::Extend(fields) => message.extension.extend(fields),
+                Statement::Field(input, field, nested) => {
+                    // Map fields with the same name would define the same entry type, which has no
+                    // location of its own, so report the field instead.
+                    let is_map = nested
+                        .as_ref()
+                        .and_then(|nested| nested.options.as_ref())
+                        .map_or(false, |o| o.map_entry());
+                    if is_map && !map_names.insert(field.name().to_owned()) {
+                        return fail(
+                            input,
+                            format!("\"{}\" is already defined in \"{}\"", field.name(), name),
+                        );
+                    }
+                    message.field.push(field);
+                    message.nested_type.extend(nested.map(|nested| *nested));
+                }
+                Statement::Empty => (),
+            }
+        }
+        add_synthetic_oneofs(&mut message);
+        Ok((input, message))
+    }
+}
+
+fn statement(input: Span) -> IResult<Statement> {
+    alt((
+        map(
+            option::parse_statement::<MessageOptions>(OPTIONS_TAG),
+            Statement::Option,
+        ),
+        map(parse(NESTED_TAG), Statement::Message),
+        map(r#enum::parse(r#enum::MESSAGE_TAG), Statement::Enum),
+        map(oneof::parse, |(oneof, fields)| {
+            Statement::Oneof(oneof, fields)
+        }),
+        map(reserved::parse_message, Statement::Reserved),
+        map(extensions, Statement::Extensions),
+        map(extend::parse(extend::MESSAGE_TAG), Statement::Extend),
+        map(end_of_declaration(';', false), |()| Statement::Empty),
+        map(field::parse, |(field, nested)| {
+            Statement::Field(input, field, nested.map(Box::new))
+        }),
+    ))(input)
+}
+
+/// Parses an `extensions` statement, such as `extensions 100 to 199, 500 to max;`.
+fn extensions(input: Span) -> IResult<Vec<ExtensionRange>> {
+    locate(
+        EXTENSION_RANGE_TAG,
+        preceded(
+            keyword("extensions"),
+            cut(map(
+                terminated(
+                    separated_list1(
+                        symbol(','),
+                        preceded(ws0, reserved::range(MAX_FIELD_NUMBER)),
+                    ),
+                    context("`;`", end_of_declaration(';', true)),
+                ),
+                |ranges| {
+                    ranges
+                        .into_iter()
+                        .map(|(start, end)| ExtensionRange {
+                            start: Some(start),
+                            end: Some(end + 1),
+                            options: None,
+                        })
+                        .collect()
+                },
+            )),
+        ),
+    )(input)
+}
+
+/// Adds a synthetic oneof for each proto3 `optional` field, as `protoc` does.
+///
+/// Synthetic oneofs follow all other oneofs, and are named after their field with a leading
+/// underscore, prefixed with `X` until the name doesn't conflict with another field or oneof.
+fn add_synthetic_oneofs(message: &mut DescriptorProto) {
+    let mut names: HashSet<String> = message
+        .field
+        .iter()
+        .map(|field| field.name().to_owned())
+        .chain(
+            message
+                .oneof_decl
+                .iter()
+                .map(|oneof| oneof.name().to_owned()),
+        )
+        .collect();
+
+    for field in &mut message.field {
+        if !field.proto3_optional() {
+            continue;
+        }
+        let mut name = format!("_{}", field.name());
+        while names.contains(&name) {
+            name.insert(0, 'X');
+        }
+        names.insert(name.clone());
+
+        field.oneof_index = Some(message.oneof_decl.len() as i32);
+        message.oneof_decl.push(OneofDescriptorProto {
+            name: Some(name),
+            options: None,
+        });
+    }
+}
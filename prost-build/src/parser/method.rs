@@ -0,0 +1,80 @@
+use nom::branch::alt;
+use nom::combinator::{cut, map, opt};
+use nom::error::context;
+use nom::multi::many0;
+use nom::sequence::{delimited, pair, preceded, terminated};
+use prost_types::{MethodDescriptorProto, MethodOptions};
+
+use super::location::{end_of_declaration, locate, Tag};
+use super::token::{keyword, symbol, ws0};
+use super::{identifier, option, IResult, Span};
+
+/// `ServiceDescriptorProto.method`
+const TAG: Tag = Tag::Repeated(2);
+/// `MethodDescriptorProto.options`
+const OPTIONS_TAG: Tag = Tag::Field(4);
+
+/// Parses an `rpc` definition, such as `rpc Foo(Request) returns (stream Response);`.
+///
+/// The method may instead be followed by a body containing its options.
+pub(super) fn parse(input: Span) -> IResult<MethodDescriptorProto> {
+    locate(
+        TAG,
+        preceded(
+            keyword("rpc"),
+            cut(|input| {
+                let (input, name) = preceded(ws0, identifier::parse)(input)?;
+                let (input, (client_streaming, input_type)) = message_type(input)?;
+                let (input, _) = preceded(ws0, context("`returns`", keyword("returns")))(input)?;
+                let (input, (server_streaming, output_type)) = message_type(input)?;
+                let (input, options) = context(
+                    "`;` or `{`",
+                    alt((map(end_of_declaration(';', true), |()| None), body)),
+                )(input)?;
+
+                Ok((
+                    input,
+                    MethodDescriptorProto {
+                        name: Some(name),
+                        input_type: Some(input_type),
+                        output_type: Some(output_type),
+                        options,
+                        client_streaming: client_streaming.then(|| true),
+                        server_streaming: server_streaming.then(|| true),
+                    },
+                ))
+            }),
+        ),
+    )(input)
+}
+
+/// Parses a parenthesized message type, such as `(stream foo.Bar)`.
+fn message_type(input: Span) -> IResult<(bool, String)> {
+    delimited(
+        context("`(`", symbol('(')),
+        pair(
+            map(
+                opt(preceded(ws0, terminated(keyword("stream"), ws0))),
+                |s| s.is_some(),
+            ),
+            preceded(ws0, identifier::parse_type),
+        ),
+        context("`)`", symbol(')')),
+    )(input)
+}
+
+/// Parses a method body, which may contain options.
+fn body(input: Span) -> IResult<Option<MethodOptions>> {
+    let (input, _) = end_of_declaration('{', true)(input)?;
+    let (input, statements) = many0(alt((
+        map(option::parse_statement::<MethodOptions>(OPTIONS_TAG), Some),
+        map(end_of_declaration(';', false), |()| None),
+    )))(input)?;
+    let (input, _) = context("`}`", end_of_declaration('}', false))(input)?;
+
+    let mut options = None;
+    for parsed in statements.into_iter().flatten() {
+        option::merge(&mut options, parsed);
+    }
+    Ok((input, options))
+}
@@ -0,0 +1,3514 @@
+//! A parser for `.proto` files.
+//!
+//! The parser produces the same [`FileDescriptorSet`] as `protoc`, including the
+//! `SourceCodeInfo` locations and comments used to generate documentation, without requiring
+//! `protoc` to be installed.
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use std::path::{Path, PathBuf};
+//!
+//! let mut files = HashMap::new();
+//! files.insert(
+//!     PathBuf::from("items.proto"),
+//!     r#"
+//!         syntax = "proto3";
+//!         package snazzy.items;
+//!
+//!         // A snazzy new shirt!
+//!         message Shirt {
+//!             string color = 1;
+//!         }
+//!     "#
+//!     .to_string(),
+//! );
+//!
+//! let descriptors = prost_build::parser::parse(&files).unwrap();
+//! assert_eq!(descriptors.file[0].message_type[0].name(), "Shirt");
+//! ```
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use nom::combinator::all_consuming;
+use nom::Slice;
+use nom_locate::LocatedSpan;
+use prost_types::field_descriptor_proto::Type;
+use prost_types::source_code_info::Location;
+use prost_types::{
+    DescriptorProto, EnumDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+    ServiceDescriptorProto, SourceCodeInfo,
+};
+
+use self::error::InputError;
+use self::location::{State, Tag};
+
+mod comment;
+mod r#enum;
+mod error;
+mod extend;
+mod field;
+mod file;
+mod identifier;
+mod import;
+mod literal;
+mod location;
+mod lookup;
+mod message;
+mod method;
+mod normalize;
+mod oneof;
+mod option;
+mod package;
+mod presence;
+mod prune;
+mod reserved;
+mod resolve;
+mod service;
+mod syntax;
+mod token;
+mod validate;
+mod warning;
+
+pub use self::error::{Error, ParseError, ResolutionError, ValidationError};
+pub use self::file::Statement;
+pub use self::import::{imports, transitive_dependencies, ImportKind};
+pub use self::lookup::{
+    defining_file, element_location, extensions, field_comments, field_type, TypeDescriptor,
+};
+pub use self::normalize::strip_comments;
+pub use self::presence::{field_presence, Presence};
+pub use self::prune::prune_to_services;
+pub use self::syntax::Syntax;
+pub use self::warning::Warning;
+
+/// The input to every parser, which tracks its position in the source and the shared parse state.
+type Span<'a> = LocatedSpan<&'a str, &'a State>;
+
+type IResult<'a, O> = nom::IResult<Span<'a>, O, InputError<'a>>;
+
+/// The result of parsing `.proto` files.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Parses a set of `.proto` files, keyed by the name they are imported by.
+///
+/// Type references are resolved across the files, and the files are returned in dependency order,
+/// with each file following the files it imports. Every file imported by another must be included
+/// in `files`, except for weak imports.
+///
+/// This is equivalent to parsing with a [`Parser`] with default options.
+pub fn parse(files: &HashMap<PathBuf, String>) -> Result<FileDescriptorSet> {
+    Parser::new().parse(files)
+}
+
+/// Options for parsing a set of `.proto` files.
+///
+/// By default, the output is the same as `protoc` would produce.
+#[derive(Clone, Debug, Default)]
+pub struct Parser {
+    sort_fields: bool,
+    lenient: bool,
+    case_insensitive_imports: bool,
+    scalar_aliases: HashMap<String, Type>,
+}
+
+impl Parser {
+    /// Creates a new parser with default options.
+    pub fn new() -> Parser {
+        Parser::default()
+    }
+
+    /// Configures the parser to sort the fields of each message by number, rather than leaving
+    /// them in declaration order.
+    ///
+    /// This makes it easier to compare the output with descriptors from other sources. The
+    /// `SourceCodeInfo` locations of the fields are updated to match.
+    pub fn sort_fields(&mut self, enabled: bool) -> &mut Self {
+        self.sort_fields = enabled;
+        self
+    }
+
+    /// Configures the parser to skip top-level statements it doesn't recognize, rather than
+    /// failing.
+    ///
+    /// This allows files using statements added by newer versions of the language to be parsed,
+    /// as long as the statement begins with an identifier and its braces are balanced. A warning
+    /// is reported for each skipped statement.
+    pub fn lenient(&mut self, enabled: bool) -> &mut Self {
+        self.lenient = enabled;
+        self
+    }
+
+    /// Configures the parser to match imports against the names of the files being parsed
+    /// ignoring case, as on a case-insensitive filesystem.
+    ///
+    /// An import which doesn't name a file exactly, but matches exactly one file when case is
+    /// ignored, is then treated as an import of that file, and a warning is reported.
+    pub fn case_insensitive_imports(&mut self, enabled: bool) -> &mut Self {
+        self.case_insensitive_imports = enabled;
+        self
+    }
+
+    /// Registers `name` as an additional name for the scalar type `ty`.
+    ///
+    /// Fields declared with the type `name` are then given the type `ty`, as if it had been
+    /// written instead. An alias takes precedence over any message or enum with the same name.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ty` is not a scalar type, i.e. if it is a message, enum or group.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use std::path::PathBuf;
+    ///
+    /// use prost_types::field_descriptor_proto::Type;
+    ///
+    /// let files = HashMap::from([(
+    ///     PathBuf::from("ids.proto"),
+    ///     r#"syntax = "proto3"; message Id { uuid value = 1; }"#.to_owned(),
+    /// )]);
+    /// let descriptors = prost_build::parser::Parser::new()
+    ///     .scalar_alias("uuid", Type::Bytes)
+    ///     .parse(&files)
+    ///     .unwrap();
+    /// assert_eq!(descriptors.file[0].message_type[0].field[0].r#type(), Type::Bytes);
+    /// ```
+    pub fn scalar_alias(&mut self, name: impl Into<String>, ty: Type) -> &mut Self {
+        assert!(
+            !matches!(ty, Type::Message | Type::Enum | Type::Group),
+            "{:?} is not a scalar type",
+            ty
+        );
+        self.scalar_aliases.insert(name.into(), ty);
+        self
+    }
+
+    /// Parses a set of `.proto` files, keyed by the name they are imported by.
+    ///
+    /// See [`parse`] for details. Any warnings are logged.
+    pub fn parse(&self, files: &HashMap<PathBuf, String>) -> Result<FileDescriptorSet> {
+        let (descriptors, warnings) = self.parse_with_warnings(files)?;
+        for warning in warnings {
+            warn!("{}", warning);
+        }
+        Ok(descriptors)
+    }
+
+    /// Parses a set of `.proto` files like [`Parser::parse`], also returning any warnings about
+    /// problems which didn't prevent the files from being parsed.
+    ///
+    /// Warnings are sorted by file and position. A warning is reported for each weak import of a
+    /// file which isn't in `files`, for each statement skipped in [lenient] mode, and for each
+    /// import matched [ignoring case].
+    ///
+    /// [lenient]: Parser::lenient
+    /// [ignoring case]: Parser::case_insensitive_imports
+    pub fn parse_with_warnings(
+        &self,
+        files: &HashMap<PathBuf, String>,
+    ) -> Result<(FileDescriptorSet, Vec<Warning>)> {
+        let names: Vec<String> = files.keys().map(|path| normalize_path(path)).collect();
+        let mut parsed = Vec::with_capacity(files.len());
+        let mut warnings = Vec::new();
+        for (path, source) in files {
+            let (mut file, _, file_warnings) = parse_file(&normalize_path(path), source, self)?;
+            for dependency in &mut file.dependency {
+                *dependency = dependency.replace('\\', "/");
+            }
+            if self.case_insensitive_imports {
+                warnings.extend(import::match_case_insensitively(&mut file, &names));
+            }
+            parsed.push(file);
+            warnings.extend(file_warnings);
+        }
+        for file in &parsed {
+            for &index in &file.weak_dependency {
+                let dependency = &file.dependency[index as usize];
+                if !names.contains(dependency) {
+                    let (line, column) = start_of(file, &[3, index]).unwrap_or_default();
+                    warnings.push(Warning {
+                        file: file.name().to_owned(),
+                        line,
+                        column,
+                        message: format!("weak import \"{}\" was not found", dependency),
+                    });
+                }
+            }
+        }
+        warnings.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+
+        parsed = resolve::sort(parsed).map_err(Error::Resolution)?;
+        // Report missing imports up front, rather than as undefined types during resolution.
+        for file in &parsed {
+            for (index, dependency) in file.dependency.iter().enumerate() {
+                let index = index as i32;
+                if !file.weak_dependency.contains(&index) && !names.contains(dependency) {
+                    return Err(Error::Resolution(ResolutionError::at(
+                        file,
+                        &[3, index],
+                        format!("imported file \"{}\" not provided", dependency),
+                    )));
+                }
+            }
+        }
+        resolve::resolve(&mut parsed).map_err(Error::Resolution)?;
+        let errors: Vec<_> = parsed.iter().flat_map(validate::validate).collect();
+        if !errors.is_empty() {
+            return Err(Error::Validation(errors));
+        }
+        if self.sort_fields {
+            parsed.iter_mut().for_each(normalize::sort_fields);
+        }
+        Ok((FileDescriptorSet { file: parsed }, warnings))
+    }
+}
+
+/// Parses a single file with the given name, without resolving its type references.
+///
+/// Type names referring to messages and enums are left as written, and the corresponding field
+/// types are unset, since resolving them may require other files.
+///
+/// ```
+/// let file = prost_build::parser::parse_str(
+///     "greeting.proto",
+///     r#"syntax = "proto3"; message Greeting { string text = 1; }"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(file.name(), "greeting.proto");
+/// assert_eq!(file.message_type[0].name(), "Greeting");
+/// assert!(file.source_code_info.is_some());
+/// ```
+pub fn parse_str(name: &str, source: &str) -> Result<FileDescriptorProto> {
+    parse_str_with_offsets(name, source).map(|(file, _)| file)
+}
+
+/// Parses a single file like [`parse_str`], also returning the byte range of each location.
+///
+/// The ranges are in the same order as the locations in the file's `SourceCodeInfo`, and index
+/// into `source`. Unlike the line and column numbers of a location, they can be used to slice the
+/// source directly, even when it contains tabs.
+///
+/// ```
+/// let source = r#"syntax = "proto3"; message Greeting {}"#;
+/// let (file, offsets) = prost_build::parser::parse_str_with_offsets("greeting.proto", source)
+///     .unwrap();
+///
+/// let locations = &file.source_code_info.unwrap().location;
+/// assert_eq!(locations.len(), offsets.len());
+/// assert_eq!(&source[offsets[2].clone()], "message Greeting {}");
+/// ```
+pub fn parse_str_with_offsets(
+    name: &str,
+    source: &str,
+) -> Result<(FileDescriptorProto, Vec<Range<usize>>)> {
+    parse_file(name, source, &Parser::default()).map(|(file, offsets, _)| (file, offsets))
+}
+
+/// Parses a single file into its top-level statements, in declaration order, without assembling
+/// them into a [`FileDescriptorProto`].
+///
+/// The statements are parsed lazily, one at a time as the iterator advances. Each statement is
+/// returned with its location, whose path is relative to the file as it would be in the file's
+/// `SourceCodeInfo`. Locations nested within a statement are not returned, and type references are
+/// left unresolved, as with [`parse_str`].
+///
+/// If the file can't be parsed, the statements before the error are still returned, followed by
+/// the error itself, after which the iterator ends.
+///
+/// ```
+/// use prost_build::parser::Statement;
+///
+/// let statements = prost_build::parser::parse_statements(
+///     "greeting.proto",
+///     "syntax = \"proto3\";\n// Says hello.\nservice Greeter {}\nmessage Greeting {}\n",
+/// );
+///
+/// let services: Vec<_> = statements
+///     .filter_map(|statement| match statement {
+///         Ok((Statement::Service(service), location)) => Some((service, location)),
+///         _ => None,
+///     })
+///     .collect();
+/// assert_eq!(services[0].0.name(), "Greeter");
+/// assert_eq!(services[0].1.leading_comments(), " Says hello.\n");
+/// ```
+pub fn parse_statements<'a>(
+    name: &'a str,
+    source: &'a str,
+) -> impl Iterator<Item = Result<(Statement, Location)>> + 'a {
+    Statements {
+        name,
+        source,
+        state: State::default(),
+        offset: None,
+        finished: false,
+    }
+}
+
+/// The iterator returned by [`parse_statements`].
+struct Statements<'a> {
+    name: &'a str,
+    source: &'a str,
+    state: State,
+    /// The offset of the next statement, or `None` if the `syntax` statement hasn't been parsed.
+    offset: Option<usize>,
+    /// Whether the end of the file or an error has been reached.
+    finished: bool,
+}
+
+impl Iterator for Statements<'_> {
+    type Item = Result<(Statement, Location)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let next = self.parse_next();
+        if !matches!(next, Some(Ok(_))) {
+            self.finished = true;
+        }
+        next
+    }
+}
+
+impl Statements<'_> {
+    fn parse_next(&mut self) -> Option<Result<(Statement, Location)>> {
+        let input = Span::new_extra(self.source, &self.state);
+        let mut input = match self.offset {
+            Some(offset) => input.slice(offset..),
+            None => match file::start(input) {
+                Ok((rest, _)) => rest,
+                Err(error) => return Some(Err(to_error(self.name, error))),
+            },
+        };
+        while !input.fragment().is_empty() {
+            match file::located_statement(input) {
+                Ok((rest, statement)) => {
+                    input = rest;
+                    self.offset = Some(rest.location_offset());
+                    if let Some((statement, index)) = statement {
+                        return Some(Ok((statement, self.state.location(index))));
+                    }
+                }
+                Err(nom::Err::Error(_)) => {
+                    return file::end(input)
+                        .err()
+                        .map(|error| Err(to_error(self.name, error)))
+                }
+                Err(error) => return Some(Err(to_error(self.name, error))),
+            }
+        }
+        None
+    }
+}
+
+/// Parses a single `message` declaration, such as one extracted from a larger file.
+///
+/// The declaration may be surrounded by comments and whitespace, but nothing else. Since the
+/// declaration isn't part of a file, its syntax must be given, and the paths of the returned
+/// locations are relative to the message rather than a file, with the message itself at the
+/// empty path. Type references are left unresolved, as with [`parse_str`].
+///
+/// ```
+/// use prost_build::parser::Syntax;
+///
+/// let (message, info) = prost_build::parser::parse_message(
+///     "greeting.proto",
+///     "// A greeting.\nmessage Greeting {\n  string text = 1;\n}\n",
+///     Syntax::Proto3,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(message.field[0].name(), "text");
+/// assert_eq!(info.location[0].leading_comments(), " A greeting.\n");
+/// assert_eq!(info.location[1].path, [2, 0]);
+/// ```
+pub fn parse_message(
+    name: &str,
+    source: &str,
+    syntax: Syntax,
+) -> Result<(DescriptorProto, SourceCodeInfo)> {
+    parse_declaration(name, source, syntax, |input| {
+        message::parse(Tag::Root)(input)
+    })
+}
+
+/// Parses a single `enum` declaration, like [`parse_message`].
+pub fn parse_enum(
+    name: &str,
+    source: &str,
+    syntax: Syntax,
+) -> Result<(EnumDescriptorProto, SourceCodeInfo)> {
+    parse_declaration(name, source, syntax, |input| {
+        r#enum::parse(Tag::Root)(input)
+    })
+}
+
+/// Parses a single `service` declaration, like [`parse_message`].
+pub fn parse_service(
+    name: &str,
+    source: &str,
+    syntax: Syntax,
+) -> Result<(ServiceDescriptorProto, SourceCodeInfo)> {
+    parse_declaration(name, source, syntax, |input| {
+        service::parse(Tag::Root)(input)
+    })
+}
+
+fn parse_declaration<O>(
+    name: &str,
+    source: &str,
+    syntax: Syntax,
+    parser: for<'a> fn(Span<'a>) -> IResult<'a, O>,
+) -> Result<(O, SourceCodeInfo)> {
+    let state = State::default();
+    state.set_syntax(syntax);
+    let (_, declaration) = all_consuming(|input| {
+        let (input, comments) = comment::collect(input, true)?;
+        input.extra.record_file_comments(comments);
+        parser(input)
+    })(Span::new_extra(source, &state))
+    .map_err(|error| to_error(name, error))?;
+    Ok((declaration, state.into_locations().0))
+}
+
+fn parse_file(
+    name: &str,
+    source: &str,
+    options: &Parser,
+) -> Result<(FileDescriptorProto, Vec<Range<usize>>, Vec<Warning>)> {
+    let mut state = State::default();
+    state.set_lenient(options.lenient);
+    state.set_scalar_aliases(options.scalar_aliases.clone());
+    let (_, mut file) = all_consuming(file::parse)(Span::new_extra(source, &state))
+        .map_err(|error| to_error(name, error))?;
+    let warnings = state
+        .skipped()
+        .into_iter()
+        .map(|position| Warning {
+            file: name.to_owned(),
+            line: position.line as usize + 1,
+            column: position.column as usize + 1,
+            message: "skipped unrecognized statement".to_owned(),
+        })
+        .collect();
+    let (source_code_info, offsets) = state.into_locations();
+    file.name = Some(name.to_owned());
+    file.source_code_info = Some(source_code_info);
+    Ok((file, offsets, warnings))
+}
+
+/// Returns the name of the file at `path`, with any backslashes replaced by forward slashes.
+///
+/// Imports are normalized in the same way, so that files keyed by Windows-style paths can still
+/// be imported.
+fn normalize_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn to_error(name: &str, error: nom::Err<InputError>) -> Error {
+    let (line, column, message) = match error {
+        nom::Err::Error(error) | nom::Err::Failure(error) => {
+            let (line, column) = error.line_column();
+            (line as usize, column, error.to_string())
+        }
+        nom::Err::Incomplete(_) => (0, 0, "unexpected end of input".to_owned()),
+    };
+    Error::Parse(ParseError {
+        file: name.to_owned(),
+        line,
+        column,
+        message,
+    })
+}
+
+/// Returns the one-based line and column at which the element at `path` starts.
+fn start_of(file: &FileDescriptorProto, path: &[i32]) -> Option<(usize, usize)> {
+    let location = file
+        .source_code_info
+        .iter()
+        .flat_map(|info| &info.location)
+        .find(|location| location.path == path)?;
+    Some((location.span[0] as usize + 1, location.span[1] as usize + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use prost_types::field_descriptor_proto::{Label, Type};
+    use prost_types::field_options::{CType, JsType};
+    use prost_types::file_options::OptimizeMode;
+    use prost_types::source_code_info::Location;
+
+    fn parse_one(source: &str) -> FileDescriptorProto {
+        parse_str("test.proto", source).unwrap()
+    }
+
+    fn parse_all(files: &[(&str, &str)]) -> Result<FileDescriptorSet> {
+        parse(
+            &files
+                .iter()
+                .map(|(name, source)| (PathBuf::from(name), source.to_string()))
+                .collect(),
+        )
+    }
+
+    fn location<'a>(file: &'a FileDescriptorProto, path: &[i32]) -> &'a Location {
+        file.source_code_info
+            .as_ref()
+            .unwrap()
+            .location
+            .iter()
+            .find(|location| location.path == path)
+            .unwrap_or_else(|| panic!("no location for {:?}", path))
+    }
+
+    #[test]
+    fn parses_file() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            package foo.bar;
+            import "other.proto";
+            import public "public.proto";
+            option java_package = "com.example.foo";
+
+            message Foo {
+                string name = 1;
+                repeated int64 ids = 2 [packed = true];
+                optional Bar bar = 3;
+                oneof choice {
+                    bool yes = 4;
+                    bool no = 5;
+                }
+                reserved 6, 10 to 12;
+                reserved "old";
+
+                message Bar {}
+                enum Kind { KIND_UNSPECIFIED = 0; }
+            }
+
+            service Service {
+                rpc Get(Foo) returns (stream Foo);
+                rpc Put(Foo) returns (Foo) {
+                    option deprecated = true;
+                }
+            }
+            "#,
+        );
+
+        assert_eq!(file.syntax(), "proto3");
+        assert_eq!(file.package(), "foo.bar");
+        assert_eq!(file.dependency, ["other.proto", "public.proto"]);
+        assert_eq!(file.public_dependency, [1]);
+        assert_eq!(file.options.unwrap().java_package(), "com.example.foo");
+
+        let message = &file.message_type[0];
+        assert_eq!(message.name(), "Foo");
+        let names: Vec<_> = message.field.iter().map(|field| field.name()).collect();
+        assert_eq!(names, ["name", "ids", "bar", "yes", "no"]);
+        assert_eq!(message.field[1].label(), Label::Repeated);
+        assert_eq!(message.field[1].r#type(), Type::Int64);
+        assert!(message.field[1].options.as_ref().unwrap().packed());
+        assert_eq!(message.field[2].type_name(), "Bar");
+        assert!(message.field[2].proto3_optional());
+        assert_eq!(message.field[3].oneof_index, Some(0));
+        assert_eq!(message.field[4].oneof_index, Some(0));
+        assert_eq!(message.field[2].oneof_index, Some(1));
+        let oneofs: Vec<_> = message
+            .oneof_decl
+            .iter()
+            .map(|oneof| oneof.name())
+            .collect();
+        assert_eq!(oneofs, ["choice", "_bar"]);
+        assert_eq!(message.reserved_range.len(), 2);
+        assert_eq!(message.reserved_range[1].start(), 10);
+        assert_eq!(message.reserved_range[1].end(), 13);
+        assert_eq!(message.reserved_name, ["old"]);
+        assert_eq!(message.nested_type[0].name(), "Bar");
+        assert_eq!(message.enum_type[0].value[0].name(), "KIND_UNSPECIFIED");
+
+        let service = &file.service[0];
+        assert_eq!(service.method[0].input_type(), "Foo");
+        assert!(service.method[0].server_streaming());
+        assert!(!service.method[0].client_streaming());
+        assert!(service.method[1].options.as_ref().unwrap().deprecated());
+    }
+
+    #[test]
+    fn labels_proto3_singular_fields_optional() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            message Foo {
+                int32 id = 1;
+                Foo next = 2;
+                optional string name = 3;
+            }
+            "#,
+        );
+
+        // Like protoc, unlabeled proto3 fields are optional, but not proto3 optional fields.
+        let fields = &file.message_type[0].field;
+        assert_eq!(fields[0].label, Some(Label::Optional as i32));
+        assert_eq!(fields[0].proto3_optional, None);
+        assert_eq!(fields[1].label, Some(Label::Optional as i32));
+        assert_eq!(fields[2].label, Some(Label::Optional as i32));
+        assert_eq!(fields[2].proto3_optional, Some(true));
+    }
+
+    #[test]
+    fn parses_fields_named_after_keywords() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            message Foo {
+                string message = 1;
+                int32 option = 2;
+                bool returns = 3;
+                repeated string reserved = 4;
+                optional int32 optional = 5;
+            }
+            "#,
+        );
+
+        let names: Vec<_> = file.message_type[0]
+            .field
+            .iter()
+            .map(|field| field.name())
+            .collect();
+        assert_eq!(
+            names,
+            ["message", "option", "returns", "reserved", "optional"]
+        );
+        assert_eq!(file.message_type[0].field[3].label(), Label::Repeated);
+        assert!(file.message_type[0].field[4].proto3_optional());
+    }
+
+    #[test]
+    fn parses_field_names_with_underscores_and_digits() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            message Foo {
+                uint64 user_id_2 = 3;
+                int32 _internal = 4;
+                string a1_b2__c3=5;
+            }
+            "#,
+        );
+
+        let fields: Vec<_> = file.message_type[0]
+            .field
+            .iter()
+            .map(|field| (field.name(), field.number(), field.json_name()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("user_id_2", 3, "userId2"),
+                ("_internal", 4, "Internal"),
+                ("a1_b2__c3", 5, "a1B2C3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_map_fields() {
+        let file = parse_one(
+            r#"
+            syntax = "proto2";
+            message Foo {
+                map<string, int32> word_counts = 1;
+                message Nested {}
+                map < int64 , Nested > by_id = 2 [deprecated = true];
+            }
+            "#,
+        );
+
+        let message = &file.message_type[0];
+        let names: Vec<_> = message.nested_type.iter().map(|m| m.name()).collect();
+        assert_eq!(names, ["WordCountsEntry", "Nested", "ByIdEntry"]);
+
+        let field = &message.field[0];
+        assert_eq!(field.label(), Label::Repeated);
+        assert_eq!(field.type_name(), "WordCountsEntry");
+        assert_eq!(field.r#type, None);
+        assert!(message.field[1].options.as_ref().unwrap().deprecated());
+
+        let entry = &message.nested_type[0];
+        assert!(entry.options.as_ref().unwrap().map_entry());
+        let fields: Vec<_> = entry
+            .field
+            .iter()
+            .map(|f| (f.name(), f.number(), f.label(), f.r#type(), f.json_name()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("key", 1, Label::Optional, Type::String, "key"),
+                ("value", 2, Label::Optional, Type::Int32, "value"),
+            ]
+        );
+        assert_eq!(message.nested_type[2].field[1].type_name(), "Nested");
+
+        // The entries take up indices of the nested types, so have no locations of their own.
+        assert_eq!(location(&file, &[4, 0, 3, 1]).span, [4, 16, 33]);
+        assert_eq!(location(&file, &[4, 0, 2, 1]).span, [5, 16, 69]);
+    }
+
+    #[test]
+    fn resolves_map_fields_to_their_entries() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            "syntax = \"proto3\";\npackage foo;\nmessage Foo {\n  map<string, int32> counts = 5;\n  map<int32, Foo> foos_2_by_id = 6;\n}",
+        )])
+        .unwrap();
+
+        let message = &descriptors.file[0].message_type[0];
+        let names: Vec<_> = message.nested_type.iter().map(|m| m.name()).collect();
+        assert_eq!(names, ["CountsEntry", "Foos2ByIdEntry"]);
+        let fields: Vec<_> = message
+            .field
+            .iter()
+            .map(|f| (f.number(), f.label(), f.r#type(), f.type_name()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (5, Label::Repeated, Type::Message, ".foo.Foo.CountsEntry"),
+                (6, Label::Repeated, Type::Message, ".foo.Foo.Foos2ByIdEntry"),
+            ]
+        );
+        let value = &message.nested_type[1].field[1];
+        assert_eq!(
+            (value.r#type(), value.type_name()),
+            (Type::Message, ".foo.Foo")
+        );
+        assert!(message.oneof_decl.is_empty());
+    }
+
+    #[test]
+    fn parses_groups() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"syntax = "proto2";
+package foo;
+message SearchResponse {
+  message Before {}
+  // A result.
+  repeated group Result = 1 [deprecated = true] { // Trailing.
+    required string url = 2;
+  }
+  optional Result last = 3;
+}
+"#,
+        )])
+        .unwrap();
+
+        let file = &descriptors.file[0];
+        let message = &file.message_type[0];
+        let field = &message.field[0];
+        assert_eq!(field.name(), "result");
+        assert_eq!(field.json_name(), "result");
+        assert_eq!(field.label(), Label::Repeated);
+        assert_eq!(field.r#type(), Type::Group);
+        assert_eq!(field.type_name(), ".foo.SearchResponse.Result");
+        assert!(field.options.as_ref().unwrap().deprecated());
+        assert_eq!(message.field[1].r#type(), Type::Message);
+        assert_eq!(message.field[1].type_name(), ".foo.SearchResponse.Result");
+
+        let group = &message.nested_type[1];
+        assert_eq!(group.name(), "Result");
+        assert_eq!(group.field[0].name(), "url");
+
+        let field_location = location(file, &[4, 0, 2, 0]);
+        assert_eq!(field_location.span, [5, 2, 7, 3]);
+        assert_eq!(field_location.leading_comments, None);
+        let group_location = location(file, &[4, 0, 3, 1]);
+        assert_eq!(group_location.span, [5, 2, 7, 3]);
+        assert_eq!(group_location.leading_comments(), " A result.\n");
+        assert_eq!(group_location.trailing_comments(), " Trailing.\n");
+        assert_eq!(location(file, &[4, 0, 3, 1, 2, 0]).span, [6, 4, 28]);
+        assert_eq!(location(file, &[4, 0, 2, 0, 8]).span, [5, 28, 47]);
+        assert_eq!(location(file, &[4, 0, 2, 1]).span, [8, 2, 27]);
+    }
+
+    #[test]
+    fn parses_message_typed_fields() {
+        let source = r#"syntax = "proto3";
+package shop;
+message Address { string street = 1; }
+message Customer {
+  message Note { string text = 1; }
+  Address home = 1;
+  repeated Customer.Note notes = 2;
+  shop.Address work = 3;
+}
+"#;
+
+        // Before resolution, the type name is left as written and the type is unset.
+        let file = parse_one(source);
+        let fields: Vec<_> = file.message_type[1]
+            .field
+            .iter()
+            .map(|field| (field.r#type, field.type_name()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (None, "Address"),
+                (None, "Customer.Note"),
+                (None, "shop.Address")
+            ]
+        );
+
+        let descriptors = parse_all(&[("test.proto", source)]).unwrap();
+        let fields: Vec<_> = descriptors.file[0].message_type[1]
+            .field
+            .iter()
+            .map(|field| (field.r#type(), field.type_name()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (Type::Message, ".shop.Address"),
+                (Type::Message, ".shop.Customer.Note"),
+                (Type::Message, ".shop.Address"),
+            ]
+        );
+    }
+
+    #[test]
+    fn distinguishes_enum_typed_fields() {
+        let descriptors = parse_all(&[
+            (
+                "a.proto",
+                r#"syntax = "proto3";
+import "b.proto";
+enum Status { STATUS_UNSPECIFIED = 0; }
+message Order {
+  enum Kind { KIND_UNSPECIFIED = 0; }
+  message Line { Kind kind = 1; }
+  Status status = 1;
+  Kind kind = 2;
+  Line line = 3;
+  Currency currency = 4;
+  Money total = 5;
+  map<string, Status> statuses = 6;
+}
+"#,
+            ),
+            (
+                "b.proto",
+                r#"syntax = "proto3";
+enum Currency { CURRENCY_UNSPECIFIED = 0; }
+message Money {}
+"#,
+            ),
+        ])
+        .unwrap();
+
+        let order = &descriptors.file[1].message_type[0];
+        let types: Vec<_> = order.field.iter().map(|field| field.r#type()).collect();
+        assert_eq!(
+            types,
+            [
+                Type::Enum,
+                Type::Enum,
+                Type::Message,
+                Type::Enum,
+                Type::Message,
+                Type::Message,
+            ]
+        );
+        assert_eq!(order.field[1].type_name(), ".Order.Kind");
+        assert_eq!(order.nested_type[0].field[0].r#type(), Type::Enum);
+        let entry = &order.nested_type[1];
+        assert_eq!(entry.field[1].r#type(), Type::Enum);
+        assert_eq!(entry.field[1].type_name(), ".Status");
+    }
+
+    #[test]
+    fn parses_proto3_optional_message_fields() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"syntax = "proto3";
+message Address {}
+message Customer {
+  optional Address home = 1;
+  optional int32 age = 2;
+  Address work = 3;
+}
+"#,
+        )])
+        .unwrap();
+
+        // As with `protoc`, `optional` marks a message field and gives it a synthetic oneof too,
+        // even though message fields always have presence.
+        let customer = &descriptors.file[0].message_type[1];
+        let fields: Vec<_> = customer
+            .field
+            .iter()
+            .map(|field| (field.name(), field.proto3_optional(), field.oneof_index))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("home", true, Some(0)),
+                ("age", true, Some(1)),
+                ("work", false, None),
+            ]
+        );
+        let oneofs: Vec<_> = customer
+            .oneof_decl
+            .iter()
+            .map(|oneof| oneof.name())
+            .collect();
+        assert_eq!(oneofs, ["_home", "_age"]);
+        assert_eq!(customer.field[0].r#type(), Type::Message);
+        assert_eq!(
+            field_presence(&customer.field[2], Syntax::Proto3),
+            Presence::Explicit
+        );
+    }
+
+    #[test]
+    fn matches_scalar_types_as_whole_names() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"syntax = "proto3";
+package int32s;
+message stringWrapper {}
+message Count {}
+message Foo {
+  string stringField = 1;
+  stringWrapper wrapped = 2;
+  int32s.Count count = 3;
+  bool boolean = 4;
+}
+"#,
+        )])
+        .unwrap();
+
+        let fields: Vec<_> = descriptors.file[0].message_type[2]
+            .field
+            .iter()
+            .map(|field| (field.name(), field.r#type(), field.type_name()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("stringField", Type::String, ""),
+                ("wrapped", Type::Message, ".int32s.stringWrapper"),
+                ("count", Type::Message, ".int32s.Count"),
+                ("boolean", Type::Bool, ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_repeated_fields() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            message Foo {
+                string name = 1;
+                repeated string tags = 3;
+                repeated  int64 ids = 4;
+                bytes data = 5;
+                repeated double weights = 6;
+                repeated bool flags = 7;
+            }
+            "#,
+        );
+
+        let fields: Vec<_> = file.message_type[0]
+            .field
+            .iter()
+            .map(|field| (field.name(), field.label(), field.r#type()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("name", Label::Optional, Type::String),
+                ("tags", Label::Repeated, Type::String),
+                ("ids", Label::Repeated, Type::Int64),
+                ("data", Label::Optional, Type::Bytes),
+                ("weights", Label::Repeated, Type::Double),
+                ("flags", Label::Repeated, Type::Bool),
+            ]
+        );
+        assert!(file.message_type[0]
+            .field
+            .iter()
+            .all(|field| !field.proto3_optional()));
+    }
+
+    #[test]
+    fn parses_labels_by_syntax() {
+        let labels = |syntax| {
+            let file = parse_one(&format!(
+                "syntax = \"{}\";\nmessage Foo {{\n  optional int32 a = 1;\n  repeated int32 b = 2;\n}}",
+                syntax
+            ));
+            file.message_type[0]
+                .field
+                .iter()
+                .map(|field| (field.label(), field.proto3_optional.is_some()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            labels("proto2"),
+            [(Label::Optional, false), (Label::Repeated, false)]
+        );
+        assert_eq!(
+            labels("proto3"),
+            [(Label::Optional, true), (Label::Repeated, false)]
+        );
+
+        let file = parse_one("syntax = \"proto2\";\nmessage Foo {\n  required int32 a = 1;\n}");
+        assert_eq!(file.message_type[0].field[0].label(), Label::Required);
+
+        let error = parse_str(
+            "test.proto",
+            "syntax = \"proto3\";\nmessage Foo {\n  required int32 a = 1;\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:3:3: required fields are not allowed in proto3"
+        );
+    }
+
+    #[test]
+    fn parses_group_labels() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"syntax = "proto2";
+message Outer {
+  optional group Inner = 1 {
+    repeated group Item = 2 {
+      required int32 id = 3;
+    }
+  }
+  required group Header = 4 {}
+}
+"#,
+        )])
+        .unwrap();
+
+        let message = &descriptors.file[0].message_type[0];
+        let fields: Vec<_> = message
+            .field
+            .iter()
+            .map(|field| {
+                (
+                    field.name(),
+                    field.label(),
+                    field.r#type(),
+                    field.type_name(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("inner", Label::Optional, Type::Group, ".Outer.Inner"),
+                ("header", Label::Required, Type::Group, ".Outer.Header"),
+            ]
+        );
+
+        let inner = &message.nested_type[0];
+        assert_eq!(inner.name(), "Inner");
+        let item = &inner.field[0];
+        assert_eq!(
+            (item.name(), item.label(), item.type_name()),
+            ("item", Label::Repeated, ".Outer.Inner.Item")
+        );
+        assert_eq!(inner.nested_type[0].name(), "Item");
+        assert_eq!(inner.nested_type[0].field[0].label(), Label::Required);
+        assert_eq!(message.nested_type[1].name(), "Header");
+    }
+
+    #[test]
+    fn rejects_invalid_groups() {
+        let error = |source| parse_str("test.proto", source).unwrap_err().to_string();
+        assert_eq!(
+            error("syntax = \"proto2\"; message Foo { optional group result = 1 {} }"),
+            "test.proto:1:49: group names must start with a capital letter"
+        );
+        assert_eq!(
+            error("syntax = \"proto3\"; message Foo { optional group Result = 1 {} }"),
+            "test.proto:1:34: groups are not supported in proto3 syntax"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_map_fields() {
+        let error = |source| parse_str("test.proto", source).unwrap_err().to_string();
+        assert_eq!(
+            error("syntax = \"proto2\"; message Foo { repeated map<int32, int32> m = 1; }"),
+            "test.proto:1:34: field labels (required/optional/repeated) are not allowed on map fields"
+        );
+        assert_eq!(
+            error("syntax = \"proto3\"; message Foo { map<double, int32> m = 1; }"),
+            "test.proto:1:38: key in map fields cannot be float/double, bytes or message types"
+        );
+        assert_eq!(
+            error("syntax = \"proto3\"; message Foo { oneof o { map<int32, int32> m = 1; } }"),
+            "test.proto:1:44: map fields are not allowed in oneofs"
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_map_fields() {
+        let error = parse_str(
+            "test.proto",
+            "syntax = \"proto3\";\nmessage M {\n  map<int32, int32> m = 1;\n  map<string, int32> m = 2;\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:4:3: \"m\" is already defined in \"M\""
+        );
+    }
+
+    #[test]
+    fn rejects_unquoted_reserved_names() {
+        let error = |source| parse_str("test.proto", source).unwrap_err().to_string();
+        assert_eq!(
+            error("syntax = \"proto3\"; message Foo { reserved foo, bar; }"),
+            "test.proto:1:43: reserved names must be quoted strings, e.g. `reserved \"foo\";`"
+        );
+        assert_eq!(
+            error("syntax = \"proto3\"; enum Foo { A = 0; reserved _b; }"),
+            "test.proto:1:47: reserved names must be quoted strings, e.g. `reserved \"foo\";`"
+        );
+
+        let file = parse_one(
+            "syntax = \"proto3\"; message Foo { reserved 1, 2 to 4; reserved \"foo\", 'bar'; }",
+        );
+        let message = &file.message_type[0];
+        assert_eq!(message.reserved_range.len(), 2);
+        assert_eq!(message.reserved_name, ["foo", "bar"]);
+    }
+
+    #[test]
+    fn parses_json_names() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            message Foo {
+                int32 foo_bar = 1;
+                int32 baz = 2 [json_name = "qux", deprecated = true];
+                int32 quux = 3 [json_name = "corge"];
+            }
+            "#,
+        );
+
+        let fields = &file.message_type[0].field;
+        assert_eq!(fields[0].json_name(), "fooBar");
+        assert_eq!(fields[1].json_name(), "qux");
+        assert!(fields[1].options.as_ref().unwrap().deprecated());
+        assert_eq!(fields[2].json_name(), "corge");
+        assert_eq!(fields[2].options, None);
+
+        assert_eq!(location(&file, &[4, 0, 2, 1, 10]).span, [4, 31, 48]);
+        assert_eq!(location(&file, &[4, 0, 2, 2, 8]).span, [5, 31, 52]);
+    }
+
+    #[test]
+    fn parses_several_field_options() {
+        let file = parse_one(
+            r#"
+            syntax = "proto2";
+            message Foo {
+                repeated int32 xs = 1 [packed = true, deprecated = true];
+            }
+            "#,
+        );
+
+        let options = file.message_type[0].field[0].options.as_ref().unwrap();
+        assert!(options.packed());
+        assert!(options.deprecated());
+        assert!(options.uninterpreted_option.is_empty());
+        assert_eq!(location(&file, &[4, 0, 2, 0, 8, 2]).span, [3, 39, 52]);
+        assert_eq!(location(&file, &[4, 0, 2, 0, 8, 3]).span, [3, 54, 71]);
+    }
+
+    #[test]
+    fn allows_trailing_commas_in_option_lists() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            message Foo {
+                int32 a = 1 [deprecated = true,];
+                int32 b = 2 [json_name = "x" , ];
+            }
+            enum Bar {
+                BAR_UNSPECIFIED = 0 [deprecated = true,];
+            }
+            "#,
+        );
+
+        let fields = &file.message_type[0].field;
+        assert!(fields[0].options.as_ref().unwrap().deprecated());
+        assert_eq!(fields[1].json_name(), "x");
+        let value = &file.enum_type[0].value[0];
+        assert!(value.options.as_ref().unwrap().deprecated());
+        assert_eq!(location(&file, &[4, 0, 2, 0, 8]).span, [3, 28, 48]);
+
+        let error = parse_str(
+            "test.proto",
+            "syntax = \"proto3\"; message Foo { int32 a = 1 [deprecated = true,,]; }",
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "test.proto:1:65: expected identifier");
+    }
+
+    #[test]
+    fn parses_ctype_and_jstype_options() {
+        let file = parse_one(
+            r#"
+            syntax = "proto2";
+            message Foo {
+                optional int64 id = 1 [jstype = JS_STRING];
+                optional string blob = 2 [ctype = CORD, deprecated = true];
+            }
+            "#,
+        );
+
+        let fields = &file.message_type[0].field;
+        let options = fields[0].options.as_ref().unwrap();
+        assert_eq!(options.jstype(), JsType::JsString);
+        assert!(options.uninterpreted_option.is_empty());
+        let options = fields[1].options.as_ref().unwrap();
+        assert_eq!(options.ctype(), CType::Cord);
+        assert!(options.deprecated());
+
+        assert_eq!(location(&file, &[4, 0, 2, 0, 8, 6]).span, [3, 39, 57]);
+        assert_eq!(location(&file, &[4, 0, 2, 1, 8, 1]).span, [4, 42, 54]);
+    }
+
+    #[test]
+    fn keeps_unknown_field_options_uninterpreted() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            message Foo {
+                string debug = 1 [retention = RETENTION_SOURCE, targets = TARGET_TYPE_FIELD];
+            }
+            "#,
+        );
+
+        let options = file.message_type[0].field[0].options.as_ref().unwrap();
+        let options: Vec<_> = options
+            .uninterpreted_option
+            .iter()
+            .map(|option| (option.name[0].name_part.as_str(), option.identifier_value()))
+            .collect();
+        assert_eq!(
+            options,
+            [
+                ("retention", "RETENTION_SOURCE"),
+                ("targets", "TARGET_TYPE_FIELD")
+            ]
+        );
+        assert_eq!(location(&file, &[4, 0, 2, 0, 8, 999, 0]).span, [3, 34, 62]);
+    }
+
+    #[test]
+    fn parses_custom_field_options() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            message Foo {
+                string name = 1 [(validate.rules).string.min_len = 1, (.my.opt) = "x"];
+            }
+            "#,
+        );
+
+        let options = file.message_type[0].field[0].options.as_ref().unwrap();
+        let rules = &options.uninterpreted_option[0];
+        let names: Vec<_> = rules
+            .name
+            .iter()
+            .map(|part| (part.name_part.as_str(), part.is_extension))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("validate.rules", true),
+                ("string", false),
+                ("min_len", false)
+            ]
+        );
+        assert_eq!(rules.positive_int_value, Some(1));
+
+        let opt = &options.uninterpreted_option[1];
+        assert_eq!(opt.name[0].name_part, ".my.opt");
+        assert!(opt.name[0].is_extension);
+        assert_eq!(opt.string_value(), b"x");
+        assert_eq!(location(&file, &[4, 0, 2, 0, 8, 999, 0]).span, [3, 33, 68]);
+    }
+
+    #[test]
+    fn parses_standalone_declarations() {
+        let (message, info) = parse_message(
+            "test.proto",
+            "message Foo {\n  // The bar.\n  optional int32 bar = 1;\n}",
+            Syntax::Proto2,
+        )
+        .unwrap();
+        assert_eq!(message.name(), "Foo");
+        assert_eq!(message.field[0].label(), Label::Optional);
+        assert_eq!(info.location[0].path, Vec::<i32>::new());
+        assert_eq!(info.location[0].span, [0, 0, 3, 1]);
+        let bar = info.location.iter().find(|l| l.path == [2, 0]).unwrap();
+        assert_eq!(bar.span, [2, 2, 25]);
+        assert_eq!(bar.leading_comments(), " The bar.\n");
+
+        let (r#enum, info) = parse_enum(
+            "test.proto",
+            "\n// Kinds.\nenum Kind { KIND_UNSPECIFIED = 0; KIND_OTHER = 1; }\n",
+            Syntax::Proto3,
+        )
+        .unwrap();
+        let values: Vec<_> = r#enum.value.iter().map(|value| value.name()).collect();
+        assert_eq!(values, ["KIND_UNSPECIFIED", "KIND_OTHER"]);
+        assert_eq!(info.location[0].span, [2, 0, 51]);
+        assert_eq!(info.location[0].leading_comments(), " Kinds.\n");
+
+        let error = parse_message(
+            "test.proto",
+            "message Foo {}\nmessage Bar {}",
+            Syntax::Proto3,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:2:1: unexpected input \"message Bar {}\""
+        );
+    }
+
+    #[test]
+    fn reports_leftover_input() {
+        let error = parse_str(
+            "test.proto",
+            "syntax = \"proto3\";\nmessage Foo {}\n}\nmessage Bar {}\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:3:1: expected top-level statement (e.g. \"message\"), found \"}\""
+        );
+
+        let error = parse_service(
+            "test.proto",
+            "service Foo {} ; service Bar { rpc Get(Request) returns (Response); }",
+            Syntax::Proto3,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:1:16: unexpected input \"; service Bar { rpc Get(Reques...\""
+        );
+    }
+
+    #[test]
+    fn checks_field_number_bounds() {
+        let file = parse_one("syntax = \"proto3\";\nmessage Foo { int32 bar = 536870911; }");
+        assert_eq!(file.message_type[0].field[0].number(), 536_870_911);
+
+        let error = |number: &str| {
+            let source = format!(
+                "syntax = \"proto3\";\nmessage Foo {{ int32 bar = {}; }}",
+                number
+            );
+            parse_str("test.proto", &source).unwrap_err().to_string()
+        };
+        assert_eq!(
+            error("536870912"),
+            "test.proto:2:27: field numbers cannot be greater than 536870911"
+        );
+        assert_eq!(
+            error("4294967296"),
+            "test.proto:2:27: field numbers cannot be greater than 536870911"
+        );
+        assert_eq!(
+            error("0"),
+            "test.proto:2:27: field numbers must be positive integers"
+        );
+        assert_eq!(
+            error("99999999999999999999"),
+            "test.proto:2:27: number too large to fit in target type"
+        );
+    }
+
+    #[test]
+    fn parses_enums_mixing_values_and_reserved() {
+        let file = parse_one(
+            r#"syntax = "proto3";
+enum Kind {
+  KIND_UNSPECIFIED = 0;
+  reserved 2 to 4, -10;
+  KIND_OTHER = 5;
+  reserved "KIND_OLD";
+  option allow_alias = true;
+}
+"#,
+        );
+
+        let r#enum = &file.enum_type[0];
+        let values: Vec<_> = r#enum
+            .value
+            .iter()
+            .map(|value| (value.name(), value.number()))
+            .collect();
+        assert_eq!(values, [("KIND_UNSPECIFIED", 0), ("KIND_OTHER", 5)]);
+        let ranges: Vec<_> = r#enum
+            .reserved_range
+            .iter()
+            .map(|range| (range.start(), range.end()))
+            .collect();
+        assert_eq!(ranges, [(2, 4), (-10, -10)]);
+        assert_eq!(r#enum.reserved_name, ["KIND_OLD"]);
+        assert!(r#enum.options.as_ref().unwrap().allow_alias());
+
+        assert_eq!(location(&file, &[5, 0, 2, 1]).span, [4, 2, 17]);
+        assert_eq!(location(&file, &[5, 0, 4]).span, [3, 2, 23]);
+        assert_eq!(location(&file, &[5, 0, 4, 1]).span, [3, 19, 22]);
+        assert_eq!(location(&file, &[5, 0, 5]).span, [5, 2, 22]);
+    }
+
+    #[test]
+    fn parses_custom_enum_options() {
+        let file = parse_one(
+            r#"syntax = "proto3";
+enum Kind {
+  option (my.enum_opt) = 5;
+  option allow_alias = true;
+  KIND_UNSPECIFIED = 0 [(my.value_opt) = "x"];
+}
+"#,
+        );
+
+        let r#enum = &file.enum_type[0];
+        let options = r#enum.options.as_ref().unwrap();
+        assert!(options.allow_alias());
+        let option = &options.uninterpreted_option[0];
+        assert_eq!(option.name[0].name_part, "my.enum_opt");
+        assert!(option.name[0].is_extension);
+        assert_eq!(option.positive_int_value, Some(5));
+        assert_eq!(location(&file, &[5, 0, 3]).span, [2, 2, 27]);
+        assert_eq!(location(&file, &[5, 0, 3, 999, 0]).span, [2, 2, 26]);
+
+        let options = r#enum.value[0].options.as_ref().unwrap();
+        assert_eq!(options.uninterpreted_option[0].string_value(), b"x");
+    }
+
+    #[test]
+    fn rejects_unknown_ctype() {
+        let error = parse_str(
+            "test.proto",
+            r#"syntax = "proto2"; message Foo { optional string s = 1 [ctype = ROPE]; }"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:1:65: option \"ctype\": enum value \"ROPE\" is not defined"
+        );
+    }
+
+    #[test]
+    fn records_enum_locations() {
+        let file = parse_one(
+            "syntax = \"proto3\";
+enum Kind {
+  KIND_UNSPECIFIED = 0;
+  KIND_OTHER = -1;
+}
+",
+        );
+
+        assert_eq!(location(&file, &[5, 0]).span, [1, 0, 4, 1]);
+        assert_eq!(location(&file, &[5, 0, 1]).span, [1, 5, 9]);
+        assert_eq!(location(&file, &[5, 0, 2, 0]).span, [2, 2, 23]);
+        assert_eq!(location(&file, &[5, 0, 2, 0, 1]).span, [2, 2, 18]);
+        assert_eq!(location(&file, &[5, 0, 2, 0, 2]).span, [2, 21, 22]);
+        assert_eq!(location(&file, &[5, 0, 2, 1]).span, [3, 2, 18]);
+        assert_eq!(location(&file, &[5, 0, 2, 1, 1]).span, [3, 2, 12]);
+        assert_eq!(location(&file, &[5, 0, 2, 1, 2]).span, [3, 15, 17]);
+    }
+
+    #[test]
+    fn parses_deeply_nested_enums() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            message A {
+                message B {
+                    message C {}
+                    enum D { D_UNSPECIFIED = 0; }
+                }
+                enum E { E_UNSPECIFIED = 0; }
+            }
+            "#,
+        );
+
+        let b = &file.message_type[0].nested_type[0];
+        assert_eq!(b.enum_type[0].name(), "D");
+        assert_eq!(file.message_type[0].enum_type[0].name(), "E");
+        assert!(file.enum_type.is_empty());
+
+        assert_eq!(location(&file, &[4, 0, 3, 0, 4, 0]).span, [5, 20, 49]);
+        assert_eq!(location(&file, &[4, 0, 3, 0, 4, 0, 2, 0]).span, [5, 29, 47]);
+        assert_eq!(location(&file, &[4, 0, 4, 0]).span, [7, 16, 45]);
+    }
+
+    #[test]
+    fn parses_nested_messages() {
+        let file = parse_one(
+            "syntax = \"proto3\";
+message Outer {
+  message Middle {
+    message Inner { int32 x = 1; }
+    Inner inner = 1;
+  }
+  message Sibling {}
+  Middle middle = 1;
+}
+",
+        );
+
+        let outer = &file.message_type[0];
+        assert_eq!(outer.field[0].name(), "middle");
+        let middle = &outer.nested_type[0];
+        assert_eq!(middle.name(), "Middle");
+        assert_eq!(middle.field[0].name(), "inner");
+        assert_eq!(middle.nested_type[0].name(), "Inner");
+        assert_eq!(middle.nested_type[0].field[0].name(), "x");
+        assert_eq!(outer.nested_type[1].name(), "Sibling");
+
+        assert_eq!(location(&file, &[4, 0, 3, 0]).span, [2, 2, 5, 3]);
+        assert_eq!(location(&file, &[4, 0, 3, 0, 3, 0]).span, [3, 4, 34]);
+        assert_eq!(location(&file, &[4, 0, 3, 0, 3, 0, 2, 0]).span, [3, 20, 32]);
+        assert_eq!(location(&file, &[4, 0, 3, 0, 2, 0]).span, [4, 4, 20]);
+        assert_eq!(location(&file, &[4, 0, 3, 1]).span, [6, 2, 20]);
+        assert_eq!(location(&file, &[4, 0, 2, 0]).span, [7, 2, 20]);
+    }
+
+    #[test]
+    fn allows_aliases_only_with_allow_alias() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            enum Foo {
+                option allow_alias = true;
+                FOO_UNSPECIFIED = 0;
+                FOO_DEFAULT = 0;
+            }
+            "#,
+        );
+        let numbers: Vec<_> = file.enum_type[0].value.iter().map(|v| v.number()).collect();
+        assert_eq!(numbers, [0, 0]);
+
+        let error = parse_str(
+            "test.proto",
+            "syntax = \"proto3\";\nenum Foo {\n  FOO_UNSPECIFIED = 0;\n  FOO_DEFAULT = 0;\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:4:3: \"FOO_DEFAULT\" uses the same enum value as \"FOO_UNSPECIFIED\"; set `option allow_alias = true;` to allow this"
+        );
+    }
+
+    #[test]
+    fn allows_allow_alias_after_values() {
+        let file = parse_one(
+            r#"
+            syntax = "proto2";
+            enum Foo {
+                FOO_UNSPECIFIED = 0;
+                FOO_DEFAULT = 0;
+                option allow_alias = true;
+                FOO_OTHER = 1;
+            }
+            "#,
+        );
+        let r#enum = &file.enum_type[0];
+        assert!(r#enum.options.as_ref().unwrap().allow_alias());
+        assert_eq!(r#enum.value.len(), 3);
+        assert_eq!(location(&file, &[5, 0, 3]).span, [5, 16, 42]);
+    }
+
+    #[test]
+    fn records_uninterpreted_options() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            option (my.option).field = -12;
+            option (agg) = { foo: 1 bar: "baz" };
+            "#,
+        );
+
+        let options = file.options.as_ref().unwrap();
+        let names: Vec<_> = options.uninterpreted_option[0]
+            .name
+            .iter()
+            .map(|part| (part.name_part.as_str(), part.is_extension))
+            .collect();
+        assert_eq!(names, [("my.option", true), ("field", false)]);
+        assert_eq!(
+            options.uninterpreted_option[0].negative_int_value,
+            Some(-12)
+        );
+        assert_eq!(
+            options.uninterpreted_option[1].aggregate_value(),
+            "foo : 1 bar : \"baz\""
+        );
+        assert_eq!(location(&file, &[8, 999, 1]).span, [3, 12, 48]);
+    }
+
+    #[test]
+    fn records_numeric_field_option_values() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            message Foo {
+                double ratio = 1 [(opt).min = -3.5, (opt).max = 2.25e1, (opt).step = -7];
+                int64 count = 2 [(opt).max = 18446744073709551615, (opt).min = -inf];
+            }
+            "#,
+        );
+
+        let options = |i: usize| {
+            file.message_type[0].field[i]
+                .options
+                .as_ref()
+                .unwrap()
+                .uninterpreted_option
+                .clone()
+        };
+        let ratio = options(0);
+        assert_eq!(ratio[0].double_value, Some(-3.5));
+        assert_eq!(ratio[0].negative_int_value, None);
+        assert_eq!(ratio[1].double_value, Some(22.5));
+        assert_eq!(ratio[2].negative_int_value, Some(-7));
+        assert_eq!(ratio[2].double_value, None);
+
+        let count = options(1);
+        assert_eq!(count[0].positive_int_value, Some(u64::MAX));
+        assert_eq!(count[1].double_value, Some(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn parses_multi_line_aggregate_options() {
+        let file = parse_one(
+            r#"syntax = "proto3";
+service S {
+  rpc Get(A) returns (B) {
+    option (google.api.http) = {
+      get: "/v1/{name=items/*}"
+      additional_bindings {
+        post: "/v1/items:get"
+        body: "*"
+      }
+    };
+  }
+  rpc List(A) returns (B);
+}
+"#,
+        );
+
+        let methods = &file.service[0].method;
+        let options = methods[0].options.as_ref().unwrap();
+        assert_eq!(
+            options.uninterpreted_option[0].aggregate_value(),
+            r#"get : "/v1/{name=items/*}" additional_bindings { post : "/v1/items:get" body : "*" }"#
+        );
+        assert_eq!(methods[1].name(), "List");
+
+        assert_eq!(location(&file, &[6, 0, 2, 0, 4]).span, [3, 4, 9, 6]);
+        assert_eq!(location(&file, &[6, 0, 2, 0]).span, [2, 2, 10, 3]);
+        assert_eq!(location(&file, &[6, 0, 2, 1]).span, [11, 2, 26]);
+    }
+
+    #[test]
+    fn parses_lists_in_aggregate_options() {
+        let file = parse_one(
+            r#"syntax = "proto3";
+message Foo {
+  int32 x = 1 [(validate.rules).int32 = { in: [1, 2, 3] not_in: [] }, deprecated = true];
+  repeated Foo y = 2 [(rules) = { items: [{ a: 1 }, { a: 2 }] }];
+}
+"#,
+        );
+
+        let fields = &file.message_type[0].field;
+        let x = fields[0].options.as_ref().unwrap();
+        assert_eq!(
+            x.uninterpreted_option[0].aggregate_value(),
+            "in : [ 1 , 2 , 3 ] not_in : [ ]"
+        );
+        assert_eq!(x.deprecated, Some(true));
+        let y = fields[1].options.as_ref().unwrap();
+        assert_eq!(
+            y.uninterpreted_option[0].aggregate_value(),
+            "items : [ { a : 1 } , { a : 2 } ]"
+        );
+        assert_eq!(fields[1].name(), "y");
+    }
+
+    #[test]
+    fn parses_language_file_options() {
+        let file = parse_one(
+            r#"syntax = "proto3";
+option optimize_for = CODE_SIZE;
+option objc_class_prefix = "FOO";
+option csharp_namespace = "Foo.Bar";
+option swift_prefix = "Swift";
+option php_class_prefix = "Php";
+option php_namespace = "Foo\\Bar";
+option php_metadata_namespace = "Foo\\Meta";
+option ruby_package = "Foo::Bar";
+"#,
+        );
+
+        let options = file.options.as_ref().unwrap();
+        assert_eq!(options.optimize_for(), OptimizeMode::CodeSize);
+        assert_eq!(options.objc_class_prefix(), "FOO");
+        assert_eq!(options.csharp_namespace(), "Foo.Bar");
+        assert_eq!(options.swift_prefix(), "Swift");
+        assert_eq!(options.php_class_prefix(), "Php");
+        assert_eq!(options.php_namespace(), "Foo\\Bar");
+        assert_eq!(options.php_metadata_namespace(), "Foo\\Meta");
+        assert_eq!(options.ruby_package(), "Foo::Bar");
+        assert!(options.uninterpreted_option.is_empty());
+        assert_eq!(location(&file, &[8, 9]).span, [1, 0, 31]);
+
+        let error = parse_str(
+            "test.proto",
+            "syntax = \"proto3\";\noption optimize_for = FAST;",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:2:23: option \"optimize_for\": enum value \"FAST\" is not defined"
+        );
+    }
+
+    #[test]
+    fn preserves_file_option_order() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            option (c) = 1;
+            option java_package = "foo";
+            option (a) = 2;
+            option (b).x = 3;
+            "#,
+        );
+
+        let options = file.options.as_ref().unwrap();
+        assert_eq!(options.java_package(), "foo");
+        let names: Vec<_> = options
+            .uninterpreted_option
+            .iter()
+            .map(|option| option.name[0].name_part.as_str())
+            .collect();
+        assert_eq!(names, ["c", "a", "b"]);
+
+        let paths: Vec<_> = file.source_code_info.as_ref().unwrap().location[2..]
+            .iter()
+            .map(|location| location.path.clone())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                vec![8],
+                vec![8, 999, 0],
+                vec![8],
+                vec![8, 1],
+                vec![8],
+                vec![8, 999, 1],
+                vec![8],
+                vec![8, 999, 2],
+            ]
+        );
+    }
+
+    #[test]
+    fn records_license_headers_as_detached_comments() {
+        let file = parse_one(
+            "/*
+ * Copyright 2024 Example Inc.
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+// Generated from the schema repository.
+
+syntax = \"proto3\";
+",
+        );
+
+        // As with `protoc`, comments before the `syntax` statement are attached to it rather than
+        // to the file's root location.
+        assert_eq!(
+            location(&file, &[]).leading_detached_comments,
+            Vec::<String>::new()
+        );
+        let syntax = location(&file, &[12]);
+        assert_eq!(syntax.span, [7, 0, 18]);
+        assert_eq!(
+            syntax.leading_detached_comments,
+            [
+                "\n Copyright 2024 Example Inc.\n Licensed under the Apache License, Version 2.0.\n",
+                " Generated from the schema repository.\n",
+            ]
+        );
+        assert_eq!(syntax.leading_comments, None);
+    }
+
+    #[test]
+    fn records_detached_comments_on_messages() {
+        let file = parse_one(
+            "syntax = \"proto3\";
+
+// Section: requests.
+
+/* Another detached comment. */
+
+// Leading.
+message Foo {
+  int32 a = 1;
+
+  // Detached from the field.
+
+  // Leading the field.
+  int32 b = 2;
+}
+",
+        );
+
+        let message = location(&file, &[4, 0]);
+        assert_eq!(
+            message.leading_detached_comments,
+            [" Section: requests.\n", " Another detached comment. "]
+        );
+        assert_eq!(message.leading_comments(), " Leading.\n");
+
+        let field = location(&file, &[4, 0, 2, 1]);
+        assert_eq!(
+            field.leading_detached_comments,
+            [" Detached from the field.\n"]
+        );
+        assert_eq!(field.leading_comments(), " Leading the field.\n");
+        assert!(location(&file, &[4, 0, 2, 0])
+            .leading_detached_comments
+            .is_empty());
+    }
+
+    #[test]
+    fn records_locations_and_comments() {
+        let file = parse_one(
+            "// Detached.
+
+syntax = \"proto3\";
+
+// Leading.
+message Foo { // Trailing.
+  // Field.
+  int32 bar = 1;
+}
+",
+        );
+
+        let syntax = location(&file, &[12]);
+        assert_eq!(syntax.span, [2, 0, 18]);
+        assert_eq!(syntax.leading_detached_comments, [" Detached.\n"]);
+
+        let message = location(&file, &[4, 0]);
+        assert_eq!(message.span, [5, 0, 8, 1]);
+        assert_eq!(message.leading_comments(), " Leading.\n");
+        assert_eq!(message.trailing_comments(), " Trailing.\n");
+
+        let field = location(&file, &[4, 0, 2, 0]);
+        assert_eq!(field.span, [7, 2, 16]);
+        assert_eq!(field.leading_comments(), " Field.\n");
+
+        assert_eq!(location(&file, &[]).span, [2, 0, 8, 1]);
+    }
+
+    #[test]
+    fn expands_tabs_in_columns() {
+        let file = parse_one(
+            "syntax = \"proto3\";\nmessage Foo {\n\tint32 bar = 1;\n  \tint32 baz = 2;\tint32 qux = 3;\n}\n",
+        );
+
+        // As with `protoc`, a tab advances the column to the next multiple of 8.
+        assert_eq!(location(&file, &[4, 0, 2, 0]).span, [2, 8, 22]);
+        assert_eq!(location(&file, &[4, 0, 2, 1]).span, [3, 8, 22]);
+        assert_eq!(location(&file, &[4, 0, 2, 2]).span, [3, 24, 38]);
+    }
+
+    #[test]
+    fn counts_columns_in_bytes() {
+        let file = parse_one(
+            "syntax = \"proto3\";\nmessage Foo {\n  int32 a = 1 [json_name = \"ä\"]; int32 b = 2;\n}\n",
+        );
+
+        // As with `protoc`, multibyte characters count as one column per byte.
+        assert_eq!(location(&file, &[4, 0, 2, 0]).span, [2, 2, 33]);
+        assert_eq!(location(&file, &[4, 0, 2, 0, 10]).span, [2, 15, 31]);
+        assert_eq!(location(&file, &[4, 0, 2, 1]).span, [2, 34, 46]);
+    }
+
+    #[test]
+    fn records_locations_of_field_parts() {
+        let file = parse_one(
+            "syntax = \"proto2\";
+message Foo {
+  repeated int32 ids = 1;
+  optional Foo next = 2;
+  optional group Bar = 3 {}
+}
+",
+        );
+
+        assert_eq!(location(&file, &[4, 0, 2, 0, 4]).span, [2, 2, 10]);
+        assert_eq!(location(&file, &[4, 0, 2, 0, 5]).span, [2, 11, 16]);
+        assert_eq!(location(&file, &[4, 0, 2, 0, 1]).span, [2, 17, 20]);
+        assert_eq!(location(&file, &[4, 0, 2, 0, 3]).span, [2, 23, 24]);
+
+        assert_eq!(location(&file, &[4, 0, 2, 1, 6]).span, [3, 11, 14]);
+        assert_eq!(location(&file, &[4, 0, 2, 1, 1]).span, [3, 15, 19]);
+
+        assert_eq!(location(&file, &[4, 0, 2, 2, 4]).span, [4, 2, 10]);
+        assert_eq!(location(&file, &[4, 0, 2, 2, 5]).span, [4, 11, 16]);
+        assert_eq!(location(&file, &[4, 0, 2, 2, 1]).span, [4, 17, 20]);
+        assert_eq!(location(&file, &[4, 0, 2, 2, 3]).span, [4, 23, 24]);
+    }
+
+    #[test]
+    fn attaches_trailing_comments_to_fields() {
+        let file = parse_one(
+            "syntax = \"proto3\";
+message Foo {
+  int32 x = 1; // id
+  int32 y = 2; /* Block. */
+  // Leading z.
+  int32 z = 3;
+}
+",
+        );
+
+        let x = location(&file, &[4, 0, 2, 0]);
+        assert_eq!(x.trailing_comments(), " id\n");
+        assert_eq!(x.leading_comments, None);
+        let y = location(&file, &[4, 0, 2, 1]);
+        assert_eq!(y.trailing_comments(), " Block. ");
+        let z = location(&file, &[4, 0, 2, 2]);
+        assert_eq!(z.leading_comments(), " Leading z.\n");
+        assert_eq!(z.trailing_comments, None);
+    }
+
+    #[test]
+    fn attaches_comments_to_oneof_map_and_extension_fields() {
+        let file = parse_one(
+            "syntax = \"proto2\";
+message Foo {
+  oneof choice {
+    // Leading a.
+    int32 a = 1; // Trailing a.
+  }
+  // Leading tags.
+  map<string, int32> tags = 2; // Trailing tags.
+  extensions 100 to 200;
+}
+extend Foo {
+  // Leading ext.
+  optional int32 ext = 100; // Trailing ext.
+}
+",
+        );
+
+        let a = location(&file, &[4, 0, 2, 0]);
+        assert_eq!(a.leading_comments(), " Leading a.\n");
+        assert_eq!(a.trailing_comments(), " Trailing a.\n");
+        let tags = location(&file, &[4, 0, 2, 1]);
+        assert_eq!(tags.leading_comments(), " Leading tags.\n");
+        assert_eq!(tags.trailing_comments(), " Trailing tags.\n");
+        let ext = location(&file, &[7, 0]);
+        assert_eq!(ext.leading_comments(), " Leading ext.\n");
+        assert_eq!(ext.trailing_comments(), " Trailing ext.\n");
+    }
+
+    #[test]
+    fn attaches_comments_between_enum_values() {
+        let file = parse_one(
+            "syntax = \"proto3\";
+enum Kind {
+  KIND_UNSPECIFIED = 0; // first
+  // second
+  KIND_SECOND = 1;
+  KIND_THIRD = 2;
+  // After the third.
+
+  KIND_FOURTH = 3;
+}
+",
+        );
+
+        let first = location(&file, &[5, 0, 2, 0]);
+        assert_eq!(first.trailing_comments(), " first\n");
+        assert_eq!(first.leading_comments, None);
+        let second = location(&file, &[5, 0, 2, 1]);
+        assert_eq!(second.leading_comments(), " second\n");
+        assert_eq!(second.trailing_comments, None);
+        let third = location(&file, &[5, 0, 2, 2]);
+        assert_eq!(third.trailing_comments(), " After the third.\n");
+        let fourth = location(&file, &[5, 0, 2, 3]);
+        assert_eq!(fourth.leading_comments, None);
+        assert!(fourth.leading_detached_comments.is_empty());
+    }
+
+    #[test]
+    fn attaches_comments_to_syntax_and_imports() {
+        let file = parse_one(
+            "// Syntax.
+syntax = \"proto3\"; // After syntax.
+
+// First import.
+import \"a.proto\";
+/* Second import. */
+import public \"b.proto\"; // After second import.
+",
+        );
+
+        let syntax = location(&file, &[12]);
+        assert_eq!(syntax.leading_comments(), " Syntax.\n");
+        assert_eq!(syntax.trailing_comments(), " After syntax.\n");
+
+        let first = location(&file, &[3, 0]);
+        assert_eq!(first.span, [4, 0, 17]);
+        assert_eq!(first.leading_comments(), " First import.\n");
+        assert_eq!(first.trailing_comments, None);
+
+        let second = location(&file, &[3, 1]);
+        assert_eq!(second.span, [6, 0, 24]);
+        assert_eq!(second.leading_comments(), " Second import. ");
+        assert_eq!(second.trailing_comments(), " After second import.\n");
+    }
+
+    #[test]
+    fn allows_comments_within_methods() {
+        let file = parse_one(
+            r#"
+            syntax = "proto3";
+            service S {
+                rpc F (A) /* x */ returns (B);
+                rpc G(A)// y
+                returns(stream /* z */ B) {}
+            }
+            "#,
+        );
+
+        let methods = &file.service[0].method;
+        assert_eq!(methods[0].input_type(), "A");
+        assert_eq!(methods[0].output_type(), "B");
+        assert_eq!(location(&file, &[6, 0, 2, 0]).leading_comments, None);
+        assert!(methods[1].server_streaming());
+    }
+
+    #[test]
+    fn parses_methods_spread_across_lines() {
+        let file = parse_one(
+            "syntax = \"proto3\";
+service S {
+  rpc Get(
+    stream Request
+  )
+    returns
+  (
+    Response
+  );
+  rpc Next(Request) returns (Response);
+}
+",
+        );
+
+        let methods = &file.service[0].method;
+        assert_eq!(methods[0].name(), "Get");
+        assert_eq!(methods[0].input_type(), "Request");
+        assert_eq!(methods[0].output_type(), "Response");
+        assert!(methods[0].client_streaming());
+        assert_eq!(location(&file, &[6, 0, 2, 0]).span, [2, 2, 8, 4]);
+        assert_eq!(location(&file, &[6, 0, 2, 1]).span, [9, 2, 39]);
+    }
+
+    #[test]
+    fn parses_statements() {
+        let statements = parse_statements(
+            "test.proto",
+            r#"syntax = "proto3";
+package foo;
+import "other.proto";
+;
+// The first.
+service A {}
+message B {}
+option java_package = "com.foo";
+service C { rpc Get(B) returns (B); }
+"#,
+        )
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+
+        let paths: Vec<_> = statements
+            .iter()
+            .map(|(_, location)| location.path.clone())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                vec![2],
+                vec![3, 0],
+                vec![6, 0],
+                vec![4, 0],
+                vec![8],
+                vec![6, 1],
+            ]
+        );
+
+        let services: Vec<_> = statements
+            .iter()
+            .filter_map(|(statement, location)| match statement {
+                Statement::Service(service) => Some((service.name(), location.span.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(services, [("A", vec![5, 0, 12]), ("C", vec![8, 0, 37])]);
+        assert_eq!(statements[2].1.leading_comments(), " The first.\n");
+        assert_eq!(
+            statements[1].0,
+            Statement::Import("other.proto".to_owned(), ImportKind::Default)
+        );
+    }
+
+    #[test]
+    fn stops_statements_at_the_first_error() {
+        let mut statements = parse_statements(
+            "test.proto",
+            "syntax = \"proto3\";\nservice A {}\nmessage B {}\nmessage {}\nservice C {}\n",
+        );
+
+        match statements.next() {
+            Some(Ok((Statement::Service(service), location))) => {
+                assert_eq!(service.name(), "A");
+                assert_eq!(location.path, [6, 0]);
+            }
+            other => panic!("expected a service, found {:?}", other),
+        }
+        assert!(matches!(
+            statements.next(),
+            Some(Ok((Statement::Message(_), _)))
+        ));
+        let error = statements.next().unwrap().unwrap_err();
+        assert_eq!(error.to_string(), "test.proto:4:9: expected identifier");
+        assert!(statements.next().is_none());
+
+        let mut statements =
+            parse_statements("test.proto", "syntax = \"proto3\";\nservice A {}\n}\n");
+        assert!(matches!(statements.next(), Some(Ok(_))));
+        assert_eq!(
+            statements.next().unwrap().unwrap_err().to_string(),
+            "test.proto:3:1: expected top-level statement (e.g. \"message\"), found \"}\""
+        );
+        assert!(statements.next().is_none());
+
+        let mut statements = parse_statements("test.proto", "");
+        assert!(statements.next().unwrap().is_err());
+        assert!(statements.next().is_none());
+    }
+
+    #[test]
+    fn indexes_enums_after_services() {
+        let file = parse_one(
+            r#"syntax = "proto3";
+service S {}
+enum E {
+  E_UNSPECIFIED = 0;
+}
+service T {}
+enum F { F_UNSPECIFIED = 0; }
+"#,
+        );
+
+        let services: Vec<_> = file.service.iter().map(|s| s.name()).collect();
+        assert_eq!(services, ["S", "T"]);
+        let enums: Vec<_> = file.enum_type.iter().map(|e| e.name()).collect();
+        assert_eq!(enums, ["E", "F"]);
+        assert_eq!(file.enum_type[0].value[0].name(), "E_UNSPECIFIED");
+
+        assert_eq!(location(&file, &[6, 0]).span, [1, 0, 12]);
+        assert_eq!(location(&file, &[5, 0]).span, [2, 0, 4, 1]);
+        assert_eq!(location(&file, &[5, 0, 2, 0]).span, [3, 2, 20]);
+        assert_eq!(location(&file, &[6, 1]).span, [5, 0, 12]);
+        assert_eq!(location(&file, &[5, 1]).span, [6, 0, 29]);
+    }
+
+    #[test]
+    fn parses_methods_with_and_without_bodies() {
+        let file = parse_one(
+            "syntax = \"proto3\";
+service S {
+  rpc F(A) returns (B) {}
+  rpc G(A) returns (B);
+  rpc H(A) returns (B) {
+    option deprecated = true;
+  };
+}
+",
+        );
+
+        let methods = &file.service[0].method;
+        let names: Vec<_> = methods.iter().map(|method| method.name()).collect();
+        assert_eq!(names, ["F", "G", "H"]);
+        assert_eq!(methods[0].options, None);
+        assert!(methods[2].options.as_ref().unwrap().deprecated());
+        assert_eq!(location(&file, &[6, 0, 2, 0]).span, [2, 2, 25]);
+        assert_eq!(location(&file, &[6, 0, 2, 2]).span, [4, 2, 6, 3]);
+
+        let error = parse_str(
+            "test.proto",
+            "syntax = \"proto3\";\nservice S {\n  rpc F(A) returns (B)\n}",
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "test.proto:4:1: expected `;` or `{`");
+    }
+
+    #[test]
+    fn parses_mixed_streaming_methods() {
+        let file = parse_one(
+            "syntax = \"proto3\";
+service S {
+  rpc Unary(X) returns (Y);
+  rpc Upload(stream X) returns (Y);
+  rpc Chat(stream X) returns (stream Y) {}
+}
+",
+        );
+
+        let methods = &file.service[0].method;
+        let flags: Vec<_> = methods
+            .iter()
+            .map(|m| (m.name(), m.client_streaming(), m.server_streaming()))
+            .collect();
+        assert_eq!(
+            flags,
+            [
+                ("Unary", false, false),
+                ("Upload", true, false),
+                ("Chat", true, true),
+            ]
+        );
+        assert_eq!(methods[0].client_streaming, None);
+        assert_eq!(location(&file, &[6, 0, 2, 0]).span, [2, 2, 27]);
+        assert_eq!(location(&file, &[6, 0, 2, 1]).span, [3, 2, 35]);
+        assert_eq!(location(&file, &[6, 0, 2, 2]).span, [4, 2, 42]);
+    }
+
+    #[test]
+    fn records_byte_offsets() {
+        let source = "syntax = \"proto2\";\n// Grüße, 世界!\nmessage Foo {\n  optional string bar = 1 [default = \"bär\"];\n}\n";
+        let (file, offsets) = parse_str_with_offsets("test.proto", source).unwrap();
+
+        let locations = &file.source_code_info.as_ref().unwrap().location;
+        assert_eq!(locations.len(), offsets.len());
+        let text = |path: &[i32]| {
+            let index = locations.iter().position(|l| l.path == path).unwrap();
+            &source[offsets[index].clone()]
+        };
+        assert_eq!(text(&[]), source.trim_end());
+        assert_eq!(
+            text(&[4, 0]),
+            "message Foo {\n  optional string bar = 1 [default = \"bär\"];\n}"
+        );
+        assert_eq!(text(&[4, 0, 2, 0, 7]), "default = \"bär\"");
+        assert_eq!(location(&file, &[4, 0, 2, 0, 7]).span, [3, 27, 43]);
+    }
+
+    #[test]
+    fn parses_comments_with_multibyte_characters() {
+        let file = parse_one(
+            "syntax = \"proto3\";
+// Ünïcödé 日本語 🎉
+message Foo {
+  /* 中文 👍 */ int32 bar = 1; // 🚀 done
+  int32 baz = 2;
+}
+",
+        );
+
+        let message = location(&file, &[4, 0]);
+        assert_eq!(message.leading_comments(), " Ünïcödé 日本語 🎉\n");
+        assert_eq!(message.span, [2, 0, 5, 1]);
+
+        let bar = location(&file, &[4, 0, 2, 0]);
+        assert_eq!(bar.leading_comments(), " 中文 👍 ");
+        assert_eq!(bar.trailing_comments(), " 🚀 done\n");
+        assert_eq!(bar.span, [3, 20, 34]);
+        assert_eq!(location(&file, &[4, 0, 2, 1]).span, [4, 2, 16]);
+    }
+
+    #[test]
+    fn locates_dotted_packages() {
+        let source = "syntax = \"proto3\";\npackage a.b.c; // Package.\n";
+        let (file, offsets) = parse_str_with_offsets("test.proto", source).unwrap();
+        assert_eq!(file.package(), "a.b.c");
+
+        let locations = &file.source_code_info.as_ref().unwrap().location;
+        let index = locations.iter().position(|l| l.path == [2]).unwrap();
+        assert_eq!(locations[index].span, [1, 0, 14]);
+        assert_eq!(&source[offsets[index].clone()], "package a.b.c;");
+        assert_eq!(locations[index].trailing_comments(), " Package.\n");
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        let error = parse_str(
+            "test.proto",
+            "syntax = \"proto3\";\nmessage Foo {\n  int32 bar 1;\n}",
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "test.proto:3:13: expected `=`");
+
+        let error = parse_str("test.proto", "syntax = \"proto4\";").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:1:10: unrecognized syntax identifier \"proto4\"; only \"proto2\" and \"proto3\" are supported"
+        );
+
+        let error = parse_str("test.proto", "edition = \"2023\";").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:1:1: editions are not supported; only \"proto2\" and \"proto3\" syntax is"
+        );
+    }
+
+    #[test]
+    fn keeps_feature_options_uninterpreted() {
+        let file = parse_one(
+            r#"syntax = "proto3";
+option features.enum_type = CLOSED;
+message Foo {
+  int32 bar = 1 [features.field_presence = EXPLICIT];
+}
+"#,
+        );
+        let option = &file.options.as_ref().unwrap().uninterpreted_option[0];
+        assert_eq!(option.identifier_value(), "CLOSED");
+
+        let options = file.message_type[0].field[0].options.as_ref().unwrap();
+        let option = &options.uninterpreted_option[0];
+        let name: Vec<_> = option
+            .name
+            .iter()
+            .map(|part| (part.name_part.as_str(), part.is_extension))
+            .collect();
+        assert_eq!(name, [("features", false), ("field_presence", false)]);
+        assert_eq!(option.identifier_value(), "EXPLICIT");
+        assert_eq!(location(&file, &[4, 0, 2, 0, 8, 999, 0]).span, [3, 17, 51]);
+    }
+
+    #[test]
+    fn rejects_empty_files() {
+        let error = parse_str("test.proto", " \n\t\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:3:1: file is empty; expected `syntax` statement"
+        );
+
+        let error = parse_str("test.proto", "// Nothing to see here.\n/* Really. */").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:2:14: file is empty; expected `syntax` statement"
+        );
+
+        let error = parse_str("test.proto", "message Foo {}").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:1:1: expected `syntax` statement"
+        );
+    }
+
+    #[test]
+    fn requires_syntax_to_be_the_first_statement() {
+        let file = parse_one(
+            "\n\n  /* License. */\n// Leading.\n\n   syntax   =\n\"proto3\"  ;\nmessage Foo {}",
+        );
+        assert_eq!(file.syntax(), "proto3");
+        assert_eq!(location(&file, &[12]).span, [5, 3, 6, 11]);
+
+        let error = parse_str(
+            "test.proto",
+            "// Leading.\nimport \"a.proto\";\nsyntax = \"proto3\";",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:2:1: expected `syntax` statement"
+        );
+
+        let error = parse_str("test.proto", ";\nsyntax = \"proto3\";").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:1:1: expected `syntax` statement"
+        );
+    }
+
+    #[test]
+    fn rejects_dotted_service_and_method_names() {
+        let error =
+            parse_str("test.proto", "syntax = \"proto3\";\nservice foo.Bar {}").unwrap_err();
+        assert_eq!(error.to_string(), "test.proto:2:12: expected `{`");
+
+        let error = parse_str(
+            "test.proto",
+            "syntax = \"proto3\";\nservice Foo {\n  rpc a.Get(A) returns (B);\n}",
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "test.proto:3:8: expected `(`");
+    }
+
+    #[test]
+    fn reports_unexpected_top_level_tokens() {
+        let error = parse_str(
+            "test.proto",
+            "syntax = \"proto3\";\n\nmesage Foo {\n  int32 bar = 1;\n}\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:3:1: expected top-level statement (e.g. \"message\"), found \"mesage\""
+        );
+    }
+
+    #[test]
+    fn skips_unknown_statements_when_lenient() {
+        let source = r#"
+            syntax = "proto3";
+            frobnicate Foo { nested { a = 1; } b = "}"; }
+            message Bar {}
+            widget = 5;
+            enum Baz { BAZ_UNSPECIFIED = 0; }
+            "#;
+        let files = HashMap::from([(PathBuf::from("test.proto"), source.to_owned())]);
+
+        let (descriptors, warnings) = Parser::new()
+            .lenient(true)
+            .parse_with_warnings(&files)
+            .unwrap();
+        let messages: Vec<_> = warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "test.proto:3:13: skipped unrecognized statement",
+                "test.proto:5:13: skipped unrecognized statement",
+            ]
+        );
+        let file = &descriptors.file[0];
+        assert_eq!(file.message_type[0].name(), "Bar");
+        assert_eq!(file.enum_type[0].name(), "Baz");
+        assert_eq!(location(file, &[4, 0]).span, [3, 12, 26]);
+
+        let error = Parser::new().parse(&files).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:3:13: expected top-level statement (e.g. \"message\"), found \"frobnicate\""
+        );
+    }
+
+    #[test]
+    fn parses_scalar_aliases() {
+        let source = r#"
+            syntax = "proto3";
+            message Foo {
+                uuid id = 1;
+                map<timestamp, uuid> history = 2;
+                repeated int32 values = 3;
+            }
+            "#;
+        let files = HashMap::from([(PathBuf::from("test.proto"), source.to_owned())]);
+
+        let descriptors = Parser::new()
+            .scalar_alias("uuid", Type::Bytes)
+            .scalar_alias("timestamp", Type::Int64)
+            .parse(&files)
+            .unwrap();
+        let message = &descriptors.file[0].message_type[0];
+        assert_eq!(message.field[0].r#type(), Type::Bytes);
+        assert_eq!(message.field[0].type_name, None);
+        assert_eq!(message.field[2].r#type(), Type::Int32);
+        let entry = &message.nested_type[0];
+        assert_eq!(entry.field[0].r#type(), Type::Int64);
+        assert_eq!(entry.field[1].r#type(), Type::Bytes);
+
+        let error = Parser::new().parse(&files).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:5:21: key in map fields cannot be float/double, bytes or message types"
+        );
+    }
+
+    #[test]
+    fn matches_imports_ignoring_case() {
+        let files = HashMap::from([
+            (
+                PathBuf::from("a.proto"),
+                "syntax = \"proto3\";\nimport \"Types/Point.proto\";\nmessage A { Point p = 1; }"
+                    .to_owned(),
+            ),
+            (
+                PathBuf::from("types/point.proto"),
+                "syntax = \"proto3\"; message Point {}".to_owned(),
+            ),
+        ]);
+
+        let (descriptors, warnings) = Parser::new()
+            .case_insensitive_imports(true)
+            .parse_with_warnings(&files)
+            .unwrap();
+        let a = descriptors
+            .file
+            .iter()
+            .find(|file| file.name() == "a.proto")
+            .unwrap();
+        assert_eq!(a.dependency, ["types/point.proto"]);
+        assert_eq!(a.message_type[0].field[0].type_name(), ".Point");
+        let messages: Vec<_> = warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            ["a.proto:2:1: import \"Types/Point.proto\" matched \"types/point.proto\" ignoring case"]
+        );
+
+        let error = Parser::new().parse(&files).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "a.proto:2:1: imported file \"Types/Point.proto\" not provided"
+        );
+    }
+
+    #[test]
+    fn normalizes_windows_paths() {
+        let files = HashMap::from([
+            (
+                PathBuf::from("protos\\a.proto"),
+                "syntax = \"proto3\";\nimport \"protos/types/b.proto\";\nmessage A { B b = 1; }"
+                    .to_owned(),
+            ),
+            (
+                PathBuf::from("protos\\types\\b.proto"),
+                "syntax = \"proto3\";\nimport \"protos\\\\c.proto\";\nmessage B { C c = 1; }"
+                    .to_owned(),
+            ),
+            (
+                PathBuf::from("protos/c.proto"),
+                "syntax = \"proto3\"; message C {}".to_owned(),
+            ),
+        ]);
+
+        let descriptors = parse(&files).unwrap();
+        let names: Vec<_> = descriptors.file.iter().map(|file| file.name()).collect();
+        assert_eq!(
+            names,
+            ["protos/c.proto", "protos/types/b.proto", "protos/a.proto"]
+        );
+        assert_eq!(descriptors.file[1].dependency, ["protos/c.proto"]);
+        assert_eq!(
+            descriptors.file[2].message_type[0].field[0].type_name(),
+            ".B"
+        );
+    }
+
+    #[test]
+    fn rejects_missing_imports() {
+        let error = parse_all(&[(
+            "a.proto",
+            "syntax = \"proto3\";\nimport \"google/protobuf/timestamp.proto\";\nmessage A {}",
+        )])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "a.proto:2:1: imported file \"google/protobuf/timestamp.proto\" not provided"
+        );
+    }
+
+    #[test]
+    fn warns_about_missing_weak_imports() {
+        let files = HashMap::from([
+            (
+                PathBuf::from("a.proto"),
+                "syntax = \"proto3\";\nimport \"b.proto\";\nimport weak \"missing.proto\";"
+                    .to_owned(),
+            ),
+            (PathBuf::from("b.proto"), "syntax = \"proto3\";".to_owned()),
+        ]);
+
+        let (descriptors, warnings) = Parser::new().parse_with_warnings(&files).unwrap();
+        assert_eq!(descriptors.file.len(), 2);
+        assert_eq!(
+            warnings,
+            [Warning {
+                file: "a.proto".to_owned(),
+                line: 3,
+                column: 1,
+                message: "weak import \"missing.proto\" was not found".to_owned(),
+            }]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "a.proto:3:1: weak import \"missing.proto\" was not found"
+        );
+    }
+
+    #[test]
+    fn treats_vertical_tabs_and_form_feeds_as_whitespace() {
+        let file = parse_one("syntax = \"proto3\";\x0cmessage\x0bFoo\x0c{\x0bint32\x0cbar = 1; }");
+        assert_eq!(file.message_type[0].field[0].name(), "bar");
+
+        // Other Unicode whitespace, such as a non-breaking space, is rejected.
+        let error =
+            parse_str("test.proto", "syntax = \"proto3\";\nmessage\u{a0}Foo {}").unwrap_err();
+        assert_eq!(error.to_string(), "test.proto:2:8: expected identifier");
+    }
+
+    #[test]
+    fn requires_labels_in_proto2() {
+        let error = parse_str(
+            "test.proto",
+            "syntax = \"proto2\";\nmessage Foo { int32 bar = 1; }",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:2:15: expected \"required\", \"optional\", or \"repeated\""
+        );
+    }
+
+    #[test]
+    fn resolves_references() {
+        let descriptors = parse_all(&[
+            (
+                "a.proto",
+                r#"
+                syntax = "proto3";
+                package foo.a;
+                import "b.proto";
+                message A {
+                    message Nested {}
+                    Nested nested = 1;
+                    b.B b = 2;
+                    .foo.b.Kind kind = 3;
+                }
+                service S {
+                    rpc Get(A) returns (foo.b.B);
+                }
+                "#,
+            ),
+            (
+                "b.proto",
+                r#"
+                syntax = "proto3";
+                package foo.b;
+                message B {}
+                enum Kind { KIND_UNSPECIFIED = 0; }
+                "#,
+            ),
+        ])
+        .unwrap();
+
+        let names: Vec<_> = descriptors.file.iter().map(|file| file.name()).collect();
+        assert_eq!(names, ["b.proto", "a.proto"]);
+
+        let a = &descriptors.file[1];
+        let fields = &a.message_type[0].field;
+        assert_eq!(fields[0].r#type(), Type::Message);
+        assert_eq!(fields[0].type_name(), ".foo.a.A.Nested");
+        assert_eq!(fields[1].r#type(), Type::Message);
+        assert_eq!(fields[1].type_name(), ".foo.b.B");
+        assert_eq!(fields[2].r#type(), Type::Enum);
+        assert_eq!(fields[2].type_name(), ".foo.b.Kind");
+
+        let method = &a.service[0].method[0];
+        assert_eq!(method.input_type(), ".foo.a.A");
+        assert_eq!(method.output_type(), ".foo.b.B");
+    }
+
+    #[test]
+    fn resolves_references_without_a_package() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"syntax = "proto3"; message M { int32 x = 1; M next = 2; }"#,
+        )])
+        .unwrap();
+
+        assert_eq!(descriptors.file.len(), 1);
+        let file = &descriptors.file[0];
+        assert_eq!(file.package, None);
+        assert!(file.dependency.is_empty());
+
+        let fields = &file.message_type[0].field;
+        assert_eq!(fields[0].r#type(), Type::Int32);
+        assert_eq!(fields[1].r#type(), Type::Message);
+        assert_eq!(fields[1].type_name(), ".M");
+    }
+
+    #[test]
+    fn resolves_oneof_member_types() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"
+            syntax = "proto3";
+            package foo;
+            message Foo {
+                oneof node {
+                    Foo child = 1;
+                    Bar bar = 2;
+                    Bar.Kind kind = 3;
+                }
+            }
+            message Bar {
+                enum Kind { KIND_UNSPECIFIED = 0; }
+            }
+            "#,
+        )])
+        .unwrap();
+
+        let fields = &descriptors.file[0].message_type[0].field;
+        assert!(fields.iter().all(|field| field.oneof_index == Some(0)));
+        assert_eq!(fields[0].r#type(), Type::Message);
+        assert_eq!(fields[0].type_name(), ".foo.Foo");
+        assert_eq!(fields[1].r#type(), Type::Message);
+        assert_eq!(fields[1].type_name(), ".foo.Bar");
+        assert_eq!(fields[2].r#type(), Type::Enum);
+        assert_eq!(fields[2].type_name(), ".foo.Bar.Kind");
+    }
+
+    #[test]
+    fn resolves_enum_map_values() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"
+            syntax = "proto3";
+            package foo;
+            enum Status { STATUS_UNSPECIFIED = 0; }
+            message Foo {
+                map<int32, Status> statuses = 1;
+            }
+            "#,
+        )])
+        .unwrap();
+
+        let message = &descriptors.file[0].message_type[0];
+        assert_eq!(message.field[0].r#type(), Type::Message);
+        assert_eq!(message.field[0].type_name(), ".foo.Foo.StatusesEntry");
+
+        let value = &message.nested_type[0].field[1];
+        assert_eq!(value.r#type(), Type::Enum);
+        assert_eq!(value.type_name(), ".foo.Status");
+    }
+
+    #[test]
+    fn qualifies_method_types_with_a_single_leading_dot() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"
+            syntax = "proto3";
+            package foo.bar;
+            message Request {}
+            message Response {}
+            service Service {
+                rpc Relative(Request) returns (bar.Response);
+                rpc Absolute(.foo.bar.Request) returns (.foo.bar.Response);
+            }
+            "#,
+        )])
+        .unwrap();
+
+        for method in &descriptors.file[0].service[0].method {
+            assert_eq!(method.input_type(), ".foo.bar.Request");
+            assert_eq!(method.output_type(), ".foo.bar.Response");
+        }
+    }
+
+    #[test]
+    fn resolves_absolute_nested_types() {
+        let descriptors = parse_all(&[
+            (
+                "a.proto",
+                r#"
+                syntax = "proto3";
+                package foo;
+                import "b.proto";
+                message Outer {
+                    message Inner {
+                        enum Kind { KIND_UNSPECIFIED = 0; }
+                    }
+                }
+                message Other {
+                    .foo.Outer.Inner inner = 1;
+                    .foo.Outer.Inner.Kind kind = 2;
+                    .bar.Outer.Inner other = 3;
+                }
+                "#,
+            ),
+            (
+                "b.proto",
+                r#"
+                syntax = "proto3";
+                package bar;
+                message Outer { message Inner {} }
+                "#,
+            ),
+        ])
+        .unwrap();
+
+        let fields = &descriptors.file[1].message_type[1].field;
+        assert_eq!(fields[0].r#type(), Type::Message);
+        assert_eq!(fields[0].type_name(), ".foo.Outer.Inner");
+        assert_eq!(fields[1].r#type(), Type::Enum);
+        assert_eq!(fields[1].type_name(), ".foo.Outer.Inner.Kind");
+        assert_eq!(fields[2].r#type(), Type::Message);
+        assert_eq!(fields[2].type_name(), ".bar.Outer.Inner");
+
+        let error = parse_all(&[(
+            "test.proto",
+            "syntax = \"proto3\";\nmessage Outer {}\nmessage Other {\n  .Outer.Missing m = 1;\n}",
+        )])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:4:3: \"Outer.Missing\" is not defined"
+        );
+    }
+
+    #[test]
+    fn suggests_scalar_types_for_typos() {
+        let error = |field| {
+            let source = format!("syntax = \"proto3\";\nmessage Foo {{\n  {}\n}}", field);
+            parse_all(&[("test.proto", &source)])
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("int x = 1;"),
+            "test.proto:3:3: \"int\" is not defined; did you mean \"int32\"?"
+        );
+        assert_eq!(
+            error("boolean x = 1;"),
+            "test.proto:3:3: \"boolean\" is not defined; did you mean \"bool\"?"
+        );
+        assert_eq!(
+            error("strng x = 1;"),
+            "test.proto:3:3: \"strng\" is not defined; did you mean \"string\"?"
+        );
+        assert_eq!(
+            error("timestamp x = 1;"),
+            "test.proto:3:3: \"timestamp\" is not defined"
+        );
+        assert_eq!(
+            error("Int x = 1;"),
+            "test.proto:3:3: \"Int\" is not defined"
+        );
+    }
+
+    #[test]
+    fn resolves_self_referential_messages() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"syntax = "proto3";
+package tree;
+message Node {
+  Node next = 1;
+  repeated Node children = 2;
+  message Leaf { Node parent = 1; Leaf sibling = 2; }
+  map<string, Node> named = 3;
+}
+"#,
+        )])
+        .unwrap();
+
+        let node = &descriptors.file[0].message_type[0];
+        let type_names: Vec<_> = node.field.iter().map(|field| field.type_name()).collect();
+        assert_eq!(
+            type_names,
+            [".tree.Node", ".tree.Node", ".tree.Node.NamedEntry"]
+        );
+        assert_eq!(node.field[1].label(), Label::Repeated);
+        let leaf = &node.nested_type[0];
+        assert_eq!(leaf.field[0].type_name(), ".tree.Node");
+        assert_eq!(leaf.field[1].type_name(), ".tree.Node.Leaf");
+        assert_eq!(node.nested_type[1].field[1].type_name(), ".tree.Node");
+    }
+
+    #[test]
+    fn resolves_references_across_syntaxes() {
+        let descriptors = parse_all(&[
+            (
+                "legacy.proto",
+                r#"
+                syntax = "proto2";
+                package legacy;
+                import "status.proto";
+                message Record {
+                    optional status.Status status = 1;
+                    required string id = 2;
+                }
+                "#,
+            ),
+            (
+                "status.proto",
+                r#"
+                syntax = "proto3";
+                package status;
+                enum Status { STATUS_UNSPECIFIED = 0; }
+                "#,
+            ),
+            (
+                "service.proto",
+                r#"
+                syntax = "proto3";
+                package service;
+                import "legacy.proto";
+                message Response {
+                    legacy.Record record = 1;
+                    int32 count = 2;
+                }
+                "#,
+            ),
+        ])
+        .unwrap();
+
+        let files: Vec<_> = descriptors
+            .file
+            .iter()
+            .map(|file| (file.name(), file.syntax()))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                ("status.proto", "proto3"),
+                ("legacy.proto", "proto2"),
+                ("service.proto", "proto3"),
+            ]
+        );
+
+        let record = &descriptors.file[1].message_type[0];
+        assert_eq!(record.field[0].r#type(), Type::Enum);
+        assert_eq!(record.field[0].type_name(), ".status.Status");
+        assert_eq!(record.field[1].label(), Label::Required);
+
+        let response = &descriptors.file[2].message_type[0];
+        assert_eq!(response.field[0].r#type(), Type::Message);
+        assert_eq!(response.field[0].type_name(), ".legacy.Record");
+        assert_eq!(response.field[1].label(), Label::Optional);
+    }
+
+    #[test]
+    fn fully_qualifies_every_type_name() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"
+            syntax = "proto3";
+            package foo.bar;
+            message Outer {
+                message Inner {
+                    Outer outer = 1;
+                    bar.Outer.Kind kind = 2;
+                }
+                enum Kind { KIND_UNSPECIFIED = 0; }
+                Inner inner = 1;
+                map<string, Inner> inners = 2;
+                oneof choice { .foo.bar.Outer outer = 3; }
+            }
+            service Service {
+                rpc Get(Outer.Inner) returns (bar.Outer);
+            }
+            "#,
+        )])
+        .unwrap();
+
+        fn type_names(message: &DescriptorProto, names: &mut Vec<String>) {
+            names.extend(message.field.iter().filter_map(|f| f.type_name.clone()));
+            for nested in &message.nested_type {
+                type_names(nested, names);
+            }
+        }
+        let file = &descriptors.file[0];
+        let mut names = Vec::new();
+        type_names(&file.message_type[0], &mut names);
+        for method in &file.service[0].method {
+            names.push(method.input_type().to_owned());
+            names.push(method.output_type().to_owned());
+        }
+        assert_eq!(
+            names,
+            [
+                ".foo.bar.Outer.Inner",
+                ".foo.bar.Outer.InnersEntry",
+                ".foo.bar.Outer",
+                ".foo.bar.Outer",
+                ".foo.bar.Outer.Kind",
+                ".foo.bar.Outer.Inner",
+                ".foo.bar.Outer.Inner",
+                ".foo.bar.Outer",
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_same_named_types_by_scope() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"
+            syntax = "proto3";
+            package foo;
+            message Status {}
+            message Job {
+                enum Status { STATUS_UNSPECIFIED = 0; }
+                Status status = 1;
+                foo.Status outer = 2;
+            }
+            message Report {
+                Status status = 1;
+                Job.Status job_status = 2;
+            }
+            "#,
+        )])
+        .unwrap();
+
+        let messages = &descriptors.file[0].message_type;
+        let types = |message: &DescriptorProto| -> Vec<_> {
+            message
+                .field
+                .iter()
+                .map(|field| (field.r#type(), field.type_name().to_owned()))
+                .collect()
+        };
+        assert_eq!(
+            types(&messages[1]),
+            [
+                (Type::Enum, ".foo.Job.Status".to_owned()),
+                (Type::Message, ".foo.Status".to_owned()),
+            ]
+        );
+        assert_eq!(
+            types(&messages[2]),
+            [
+                (Type::Message, ".foo.Status".to_owned()),
+                (Type::Enum, ".foo.Job.Status".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_qualified_types_named_like_scalars() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"
+            syntax = "proto3";
+            package foo;
+            message string {}
+            message Bar {
+                .foo.string absolute = 1;
+                foo.string relative = 2;
+                string scalar = 3;
+            }
+            "#,
+        )])
+        .unwrap();
+
+        let fields = &descriptors.file[0].message_type[1].field;
+        assert_eq!(fields[0].r#type(), Type::Message);
+        assert_eq!(fields[0].type_name(), ".foo.string");
+        assert_eq!(fields[1].r#type(), Type::Message);
+        assert_eq!(fields[1].type_name(), ".foo.string");
+        assert_eq!(fields[2].r#type(), Type::String);
+        assert_eq!(fields[2].type_name, None);
+    }
+
+    #[test]
+    fn resolves_names_shadowing_packages() {
+        let package = (
+            "package.proto",
+            "syntax = \"proto3\";\npackage a.b;\nmessage c {}\n",
+        );
+
+        let error = parse_all(&[
+            package,
+            (
+                "message.proto",
+                "syntax = \"proto3\";\npackage a;\nmessage b {\n  message c {}\n}\n",
+            ),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "package.proto:2:1: \"a.b\" is already defined in \"message.proto\""
+        );
+
+        // As with `protoc`, the first component of a relative name is matched in the innermost
+        // scope, so `a.b.c` within `z` refers to `z.a`, and only the absolute name reaches the
+        // package.
+        let shadowing = "syntax = \"proto3\";
+package z;
+import \"package.proto\";
+message a {
+  message b {}
+}
+message User {
+  .a.b.c absolute = 1;
+}
+";
+        let descriptors = parse_all(&[package, ("z.proto", shadowing)]).unwrap();
+        let field = &descriptors.file[1].message_type[1].field[0];
+        assert_eq!(field.type_name(), ".a.b.c");
+
+        let relative = shadowing.replace(".a.b.c absolute", "a.b.c relative");
+        let error = parse_all(&[package, ("z.proto", &relative)]).unwrap_err();
+        assert_eq!(error.to_string(), "z.proto:8:3: \"a.b.c\" is not defined");
+    }
+
+    #[test]
+    fn reports_duplicate_definitions_at_the_second_one() {
+        let error = parse_all(&[(
+            "t.proto",
+            "syntax = \"proto3\";\nmessage M {}\nmessage N {\n  message E {}\n  enum E { A = 0; }\n}\n",
+        )])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "t.proto:5:3: \"N.E\" is already defined in \"t.proto\""
+        );
+
+        let error = parse_all(&[(
+            "t.proto",
+            "syntax = \"proto3\";\nmessage M {}\n  service M {}\n",
+        )])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "t.proto:3:3: \"M\" is already defined in \"t.proto\""
+        );
+    }
+
+    #[test]
+    fn reports_the_file_declaring_a_conflicting_package() {
+        let error = parse_all(&[
+            ("a.proto", "syntax = \"proto3\";\npackage a.b;\n"),
+            (
+                "b.proto",
+                "syntax = \"proto3\";\npackage a;\nmessage b {}\n",
+            ),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "b.proto:3:1: \"a.b\" is already defined in \"a.proto\""
+        );
+    }
+
+    #[test]
+    fn scopes_enum_values_like_cpp() {
+        let error = parse_all(&[(
+            "t.proto",
+            "syntax = \"proto3\";\nenum E { A = 0; }\nenum F { A = 0; }\n",
+        )])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "t.proto:3:10: \"A\" is already defined in \"t.proto\"; enum values are siblings of their enum, so their names must be unique within the enclosing scope"
+        );
+
+        // Values of enums in different scopes don't conflict.
+        parse_all(&[(
+            "t.proto",
+            "syntax = \"proto3\";\nenum E { A = 0; }\nmessage M {\n  enum F { A = 0; }\n}\n",
+        )])
+        .unwrap();
+    }
+
+    #[test]
+    fn reports_unresolved_references() {
+        let error = parse_all(&[
+            (
+                "a.proto",
+                "syntax = \"proto3\";\nmessage A {\n  B b = 1;\n}",
+            ),
+            ("b.proto", "syntax = \"proto3\";\nmessage B {}"),
+        ])
+        .unwrap_err();
+        assert_eq!(error.to_string(), "a.proto:3:3: \"B\" is not defined");
+    }
+
+    #[test]
+    fn resolves_types_through_public_imports() {
+        let files = [
+            (
+                "c.proto",
+                "syntax = \"proto3\";\nimport \"a.proto\";\nmessage C {\n  B b = 1;\n}",
+            ),
+            (
+                "a.proto",
+                "syntax = \"proto3\";\nimport public \"b.proto\";\nimport \"d.proto\";",
+            ),
+            (
+                "b.proto",
+                "syntax = \"proto3\";\nimport public \"d.proto\";\nmessage B {}",
+            ),
+            ("d.proto", "syntax = \"proto3\";\nmessage D {}"),
+        ];
+        let descriptors = parse_all(&files).unwrap();
+        let c = descriptors
+            .file
+            .iter()
+            .find(|file| file.name() == "c.proto");
+        assert_eq!(c.unwrap().message_type[0].field[0].type_name(), ".B");
+
+        // Public imports are followed transitively, but ordinary imports aren't.
+        let mut files = files;
+        files[0].1 = "syntax = \"proto3\";\nimport \"a.proto\";\nmessage C {\n  D d = 1;\n}";
+        let descriptors = parse_all(&files).unwrap();
+        let c = descriptors
+            .file
+            .iter()
+            .find(|file| file.name() == "c.proto");
+        assert_eq!(c.unwrap().message_type[0].field[0].type_name(), ".D");
+
+        files[1].1 = "syntax = \"proto3\";\nimport \"b.proto\";\nimport \"d.proto\";";
+        let error = parse_all(&files).unwrap_err();
+        assert_eq!(error.to_string(), "c.proto:4:3: \"D\" is not defined");
+    }
+
+    #[test]
+    fn parses_default_values() {
+        let descriptors = parse_all(&[(
+            "test.proto",
+            r#"
+            syntax = "proto2";
+            enum Color {
+                COLOR_RED = 0;
+                COLOR_BLUE = 1;
+            }
+            message Foo {
+                optional Color color = 1 [default = COLOR_RED];
+                optional sint32 min = 2 [default = -2147483648];
+                optional string name = 3 [default = "a\x62c"];
+                optional bytes data = 4 [default = "\0\n"];
+                optional double ratio = 5 [default = -inf];
+            }
+            "#,
+        )])
+        .unwrap();
+
+        let fields = &descriptors.file[0].message_type[0].field;
+        assert_eq!(fields[0].r#type(), Type::Enum);
+        assert_eq!(fields[0].default_value(), "COLOR_RED");
+        assert_eq!(fields[1].default_value(), "-2147483648");
+        assert_eq!(fields[2].default_value(), "abc");
+        assert_eq!(fields[3].default_value(), "\\000\\n");
+        assert_eq!(fields[4].default_value(), "-inf");
+    }
+
+    #[test]
+    fn parses_default_values_of_each_kind() {
+        let file = parse_one(
+            r#"
+            syntax = "proto2";
+            enum Color { RED = 0; BLUE = 1; }
+            message Foo {
+                optional int32 a = 1 [default = 42];
+                optional int64 b = 2 [default = -0x10];
+                optional uint64 c = 3 [default = 0777];
+                optional float d = 4 [default = 2.5];
+                optional double e = 5 [default = 7];
+                optional bool f = 6 [default = true];
+                optional string g = 7 [default = 'it\'s'];
+                optional bytes h = 8 [default = "\xff"];
+                optional Color i = 9 [default = BLUE];
+            }
+            "#,
+        );
+
+        let defaults: Vec<_> = file.message_type[0]
+            .field
+            .iter()
+            .map(|field| field.default_value())
+            .collect();
+        assert_eq!(
+            defaults,
+            ["42", "-16", "511", "2.5", "7", "true", "it's", "\\377", "BLUE"]
+        );
+
+        let error = parse_str(
+            "test.proto",
+            "syntax = \"proto2\";\nmessage Foo {\n  optional bool a = 1 [default = 1];\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:3:34: expected `true` or `false`"
+        );
+    }
+
+    #[test]
+    fn formats_float_default_values() {
+        let file = parse_one(
+            r#"
+            syntax = "proto2";
+            message Foo {
+                optional double a = 1 [default = inf];
+                optional double b = 2 [default = -inf];
+                optional float c = 3 [default = nan];
+                optional double d = 4 [default = 1.5e3];
+                optional double e = 5 [default = 1e20];
+                optional double f = 6 [default = 0.1];
+                optional float g = 7 [default = 0.1];
+                optional float h = 8 [default = 3.14159265];
+                optional double i = 9 [default = -2.5E-7];
+                optional double j = 10 [default = 42];
+            }
+            "#,
+        );
+
+        let defaults: Vec<_> = file.message_type[0]
+            .field
+            .iter()
+            .map(|field| field.default_value())
+            .collect();
+        assert_eq!(
+            defaults,
+            [
+                "inf",
+                "-inf",
+                "nan",
+                "1500",
+                "1e+20",
+                "0.1",
+                "0.1",
+                "3.14159274",
+                "-2.5e-07",
+                "42"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_default_values() {
+        let error = parse_all(&[(
+            "test.proto",
+            "syntax = \"proto2\";\nmessage Foo {\n  optional uint32 a = 1 [default = -1];\n}",
+        )])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:3:36: unsigned field can't have negative default value"
+        );
+
+        let error = parse_all(&[(
+            "test.proto",
+            "syntax = \"proto2\";\nenum E { A = 0; }\nmessage Foo {\n  optional E e = 1 [default = B];\n}",
+        )])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:4:3: enum type \"E\" has no value named \"B\""
+        );
+
+        let error = parse_all(&[(
+            "test.proto",
+            "syntax = \"proto2\";\nenum E { A = 0; }\nmessage Foo {\n  optional E e = 1 [default = \"A\"];\n}",
+        )])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.proto:4:3: enum defaults must be identifiers"
+        );
+    }
+
+    #[test]
+    fn categorizes_errors() {
+        let error = parse_all(&[("a.proto", "syntax = \"proto3\";\nmessage {}")]).unwrap_err();
+        match error {
+            Error::Parse(error) => {
+                assert_eq!(
+                    (error.file.as_str(), error.line, error.column),
+                    ("a.proto", 2, 9)
+                );
+                assert_eq!(error.message, "expected identifier");
+            }
+            error => panic!("expected a parse error, found {:?}", error),
+        }
+
+        let error =
+            parse_all(&[("a.proto", "syntax = \"proto3\";\nmessage A { B b = 1; }")]).unwrap_err();
+        match error {
+            Error::Resolution(error) => {
+                assert_eq!(
+                    (error.file.as_str(), error.line, error.column),
+                    ("a.proto", 2, 13)
+                );
+                assert_eq!(error.message, "\"B\" is not defined");
+            }
+            error => panic!("expected a resolution error, found {:?}", error),
+        }
+
+        let error = parse_all(&[(
+            "a.proto",
+            "syntax = \"proto2\";\nmessage A {\n  repeated string a = 1 [packed = true];\n  repeated bytes b = 2 [packed = true];\n}",
+        )])
+        .unwrap_err();
+        let message = error.to_string();
+        match error {
+            Error::Validation(errors) => {
+                let lines: Vec<_> = errors.iter().map(|error| error.line).collect();
+                assert_eq!(lines, [3, 4]);
+            }
+            error => panic!("expected validation errors, found {:?}", error),
+        }
+        assert_eq!(message.lines().count(), 2);
+
+        let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        let error = Error::from(io_error);
+        assert!(matches!(&error, Error::Io(error) if error.kind() == std::io::ErrorKind::NotFound));
+        let io_error = std::io::Error::from(error);
+        assert_eq!(io_error.kind(), std::io::ErrorKind::NotFound);
+
+        let error = parse_all(&[("a.proto", "syntax = \"proto3\";\nmessage {}")]).unwrap_err();
+        let io_error = std::io::Error::from(error);
+        assert_eq!(io_error.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(io_error.to_string(), "a.proto:2:9: expected identifier");
+    }
+
+    #[test]
+    fn reports_import_cycles() {
+        let error = parse_all(&[
+            ("a.proto", "syntax = \"proto3\"; import \"b.proto\";"),
+            ("b.proto", "syntax = \"proto3\"; import \"a.proto\";"),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "b.proto:1:20: file recursively imports itself: a.proto -> b.proto -> a.proto"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use prost_types::{DescriptorProto, FileDescriptorProto, FileDescriptorSet};
+
+/// Removes the comments from the source code info of every file in `descriptors`.
+///
+/// Every location is kept with its span, so the result still maps elements to their source, but
+/// is smaller. To drop the locations as well, clear each file's `source_code_info` instead.
+///
+/// ```
+/// let file = prost_build::parser::parse_str(
+///     "greeting.proto",
+///     "syntax = \"proto3\";\n// A greeting.\nmessage Greeting {}\n",
+/// )
+/// .unwrap();
+/// let mut descriptors = prost_types::FileDescriptorSet { file: vec![file] };
+///
+/// prost_build::parser::strip_comments(&mut descriptors);
+/// let location = &descriptors.file[0].source_code_info.as_ref().unwrap().location[2];
+/// assert_eq!(location.span, [2, 0, 19]);
+/// assert_eq!(location.leading_comments, None);
+/// ```
+pub fn strip_comments(descriptors: &mut FileDescriptorSet) {
+    let locations = descriptors
+        .file
+        .iter_mut()
+        .flat_map(|file| &mut file.source_code_info)
+        .flat_map(|info| &mut info.location);
+    for location in locations {
+        location.leading_comments = None;
+        location.trailing_comments = None;
+        location.leading_detached_comments.clear();
+    }
+}
+
+/// Sorts the fields of every message in `file` by number, updating their locations to match.
+pub(super) fn sort_fields(file: &mut FileDescriptorProto) {
+    let mut moves = Vec::new();
+    for (i, message) in file.message_type.iter_mut().enumerate() {
+        sort_message_fields(message, &mut vec![4, i as i32], &mut moves);
+    }
+
+    let locations = file
+        .source_code_info
+        .iter_mut()
+        .flat_map(|info| &mut info.location);
+    for location in locations {
+        for (message_path, new_indices) in &moves {
+            let len = message_path.len();
+            if location.path.len() >= len + 2
+                && location.path.starts_with(message_path)
+                && location.path[len] == 2
+            {
+                location.path[len + 1] = new_indices[location.path[len + 1] as usize];
+            }
+        }
+    }
+}
+
+/// Sorts the fields of `message` and its nested messages.
+///
+/// For each message whose fields are reordered, its path and the new index of each field are
+/// added to `moves`.
+fn sort_message_fields(
+    message: &mut DescriptorProto,
+    path: &mut Vec<i32>,
+    moves: &mut Vec<(Vec<i32>, Vec<i32>)>,
+) {
+    let mut order: Vec<usize> = (0..message.field.len()).collect();
+    order.sort_by_key(|&i| message.field[i].number());
+    if order.iter().enumerate().any(|(new, &old)| new != old) {
+        let mut new_indices = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            new_indices[old] = new as i32;
+        }
+        let mut fields: Vec<_> = message.field.drain(..).map(Some).collect();
+        message.field = order.iter().map(|&i| fields[i].take().unwrap()).collect();
+        moves.push((path.clone(), new_indices));
+    }
+
+    for (i, nested) in message.nested_type.iter_mut().enumerate() {
+        path.extend([3, i as i32]);
+        sort_message_fields(nested, path, moves);
+        path.truncate(path.len() - 2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::parser::Parser;
+
+    const SOURCE: &str = r#"syntax = "proto3";
+message Foo {
+  int32 c = 3;
+  oneof choice {
+    int32 a = 1;
+    int32 b = 2;
+  }
+  message Bar {
+    int32 y = 2;
+    int32 x = 1;
+  }
+}
+"#;
+
+    fn field_names(parser: &Parser) -> (Vec<String>, Vec<String>) {
+        let files = HashMap::from([(PathBuf::from("test.proto"), SOURCE.to_owned())]);
+        let descriptors = parser.parse(&files).unwrap();
+        let message = &descriptors.file[0].message_type[0];
+        let names = |message: &prost_types::DescriptorProto| {
+            message
+                .field
+                .iter()
+                .map(|field| field.name().to_owned())
+                .collect()
+        };
+        (names(message), names(&message.nested_type[0]))
+    }
+
+    #[test]
+    fn keeps_declaration_order_by_default() {
+        let (fields, nested) = field_names(&Parser::new());
+        assert_eq!(fields, ["c", "a", "b"]);
+        assert_eq!(nested, ["y", "x"]);
+    }
+
+    #[test]
+    fn sorts_fields_by_number() {
+        let (fields, nested) = field_names(Parser::new().sort_fields(true));
+        assert_eq!(fields, ["a", "b", "c"]);
+        assert_eq!(nested, ["x", "y"]);
+
+        let files = HashMap::from([(PathBuf::from("test.proto"), SOURCE.to_owned())]);
+        let descriptors = Parser::new().sort_fields(true).parse(&files).unwrap();
+        let file = &descriptors.file[0];
+        let message = &file.message_type[0];
+        assert_eq!(message.field[0].oneof_index, Some(0));
+        assert_eq!(message.field[2].oneof_index, None);
+
+        let span = |path: &[i32]| {
+            let locations = &file.source_code_info.as_ref().unwrap().location;
+            let location = locations.iter().find(|l| l.path == path).unwrap();
+            location.span.clone()
+        };
+        assert_eq!(span(&[4, 0, 2, 0]), [4, 4, 16]);
+        assert_eq!(span(&[4, 0, 2, 2]), [2, 2, 14]);
+        assert_eq!(span(&[4, 0, 3, 0, 2, 0]), [9, 4, 16]);
+    }
+
+    #[test]
+    fn strips_comments() {
+        let source = r#"// Detached.
+
+// Leading.
+syntax = "proto3";
+message Foo {
+  int32 bar = 1; // Trailing.
+}
+"#;
+        let files = HashMap::from([(PathBuf::from("test.proto"), source.to_owned())]);
+        let mut descriptors = Parser::new().parse(&files).unwrap();
+        let spans = |descriptors: &FileDescriptorSet| {
+            let info = descriptors.file[0].source_code_info.as_ref().unwrap();
+            info.location
+                .iter()
+                .map(|location| location.span.clone())
+                .collect::<Vec<_>>()
+        };
+        let before = spans(&descriptors);
+        let info = descriptors.file[0].source_code_info.as_ref().unwrap();
+        assert_eq!(info.location[1].leading_comments(), " Leading.\n");
+
+        strip_comments(&mut descriptors);
+        assert_eq!(spans(&descriptors), before);
+        let info = descriptors.file[0].source_code_info.as_ref().unwrap();
+        assert!(info.location.iter().all(|location| {
+            location.leading_comments.is_none()
+                && location.trailing_comments.is_none()
+                && location.leading_detached_comments.is_empty()
+        }));
+    }
+}
@@ -0,0 +1,65 @@
+use nom::branch::alt;
+use nom::combinator::{cut, map};
+use nom::error::context;
+use nom::multi::many0;
+use nom::sequence::preceded;
+use prost_types::{FieldDescriptorProto, OneofDescriptorProto, OneofOptions};
+
+use super::location::{end_of_declaration, locate, Tag};
+use super::token::{keyword, ws0};
+use super::{field, identifier, option, IResult, Span};
+
+/// `DescriptorProto.oneof_decl`
+const TAG: Tag = Tag::Repeated(8);
+/// `OneofDescriptorProto.options`
+const OPTIONS_TAG: Tag = Tag::Field(2);
+
+enum Statement {
+    Option(OneofOptions),
+    Field(FieldDescriptorProto),
+    Empty,
+}
+
+/// Parses a `oneof` definition, returning it along with its fields.
+///
+/// The fields belong to the enclosing message, so the caller is responsible for adding them to
+/// the message and setting their `oneof_index`.
+pub(super) fn parse(input: Span) -> IResult<(OneofDescriptorProto, Vec<FieldDescriptorProto>)> {
+    locate(
+        TAG,
+        preceded(
+            keyword("oneof"),
+            cut(|input| {
+                let (input, name) = preceded(ws0, identifier::parse)(input)?;
+                let (input, _) = context("`{`", end_of_declaration('{', true))(input)?;
+                let (input, statements) = many0(statement)(input)?;
+                let (input, _) = context("`}`", end_of_declaration('}', false))(input)?;
+
+                let mut oneof = OneofDescriptorProto {
+                    name: Some(name),
+                    options: None,
+                };
+                let mut fields = Vec::new();
+                for statement in statements {
+                    match statement {
+                        Statement::Option(options) => option::merge(&mut oneof.options, options),
+                        Statement::Field(field) => fields.push(field),
+                        Statement::Empty => (),
+                    }
+                }
+                Ok((input, (oneof, fields)))
+            }),
+        ),
+    )(input)
+}
+
+fn statement(input: Span) -> IResult<Statement> {
+    alt((
+        map(
+            option::parse_statement::<OneofOptions>(OPTIONS_TAG),
+            Statement::Option,
+        ),
+        map(end_of_declaration(';', false), |()| Statement::Empty),
+        map(field::parse_oneof_field, Statement::Field),
+    ))(input)
+}
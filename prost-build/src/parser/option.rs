@@ -0,0 +1,576 @@
+use nom::branch::alt;
+use nom::character::complete::char;
+use nom::combinator::{cut, fail as fail_parser, map, map_res, peek};
+use nom::error::context;
+use nom::multi::separated_list1;
+use nom::sequence::{delimited, pair, preceded, terminated};
+use prost::Message;
+use prost_types::uninterpreted_option::NamePart;
+use prost_types::{
+    field_options, file_options, method_options, EnumOptions, EnumValueOptions,
+    ExtensionRangeOptions, FieldOptions, FileOptions, MessageOptions, MethodOptions, OneofOptions,
+    ServiceOptions, UninterpretedOption,
+};
+
+use super::error::fail;
+use super::location::{end_of_declaration, locate, Tag};
+use super::token::{self, keyword, symbol, ws0};
+use super::{identifier, literal, IResult, Span};
+
+/// `*Options.uninterpreted_option`
+const UNINTERPRETED_OPTION_TAG: Tag = Tag::Repeated(999);
+
+/// The value assigned to an option.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum Value {
+    Identifier(String),
+    PositiveInt(u64),
+    NegativeInt(i64),
+    Double(f64),
+    String(Vec<u8>),
+    Aggregate(String),
+}
+
+impl Value {
+    fn bool(&self) -> Result<bool, String> {
+        match self {
+            Value::Identifier(ident) if ident == "true" => Ok(true),
+            Value::Identifier(ident) if ident == "false" => Ok(false),
+            _ => Err("value must be \"true\" or \"false\"".to_string()),
+        }
+    }
+
+    fn string(&self) -> Result<String, String> {
+        match self {
+            Value::String(bytes) => String::from_utf8(bytes.clone())
+                .map_err(|_| "value must be valid UTF-8".to_string()),
+            _ => Err("value must be a quoted string".to_string()),
+        }
+    }
+
+    fn identifier(&self) -> Result<&str, String> {
+        match self {
+            Value::Identifier(ident) => Ok(ident),
+            _ => Err("value must be an identifier".to_string()),
+        }
+    }
+
+    /// Parses an enum value by name, using `from_str_name` to map names to variants.
+    fn enumeration<E>(&self, from_str_name: fn(&str) -> Option<E>) -> Result<i32, String>
+    where
+        E: Into<i32>,
+    {
+        let ident = self.identifier()?;
+        from_str_name(ident)
+            .map(Into::into)
+            .ok_or_else(|| format!("enum value \"{}\" is not defined", ident))
+    }
+
+    fn into_uninterpreted_option(self, name: Vec<NamePart>) -> UninterpretedOption {
+        let mut option = UninterpretedOption {
+            name,
+            ..UninterpretedOption::default()
+        };
+        match self {
+            Value::Identifier(value) => option.identifier_value = Some(value),
+            Value::PositiveInt(value) => option.positive_int_value = Some(value),
+            Value::NegativeInt(value) => option.negative_int_value = Some(value),
+            Value::Double(value) => option.double_value = Some(value),
+            Value::String(value) => option.string_value = Some(value),
+            Value::Aggregate(value) => option.aggregate_value = Some(value),
+        }
+        option
+    }
+}
+
+/// An options message, such as `FileOptions` or `FieldOptions`.
+pub(super) trait Options: Message + Default {
+    /// Returns the field number of the built-in option with the given name.
+    fn field_number(name: &str) -> Option<i32>;
+
+    /// Sets the built-in option with the given field number.
+    fn set(&mut self, number: i32, value: &Value) -> Result<(), String>;
+
+    fn uninterpreted_option(&mut self) -> &mut Vec<UninterpretedOption>;
+}
+
+/// Merges the options parsed from a single statement into the options of a descriptor.
+///
+/// Built-in options replace any earlier value, while uninterpreted options are appended, which
+/// matches Protobuf's merge semantics.
+pub(super) fn merge<T: Options>(options: &mut Option<T>, parsed: T) {
+    match options {
+        Some(options) => options
+            .merge(parsed.encode_to_vec().as_slice())
+            .expect("failed to merge options"),
+        None => *options = Some(parsed),
+    }
+}
+
+/// Parses an `option name = value;` statement.
+///
+/// `tag` is the tag of the options field within the enclosing descriptor.
+pub(super) fn parse_statement<'a, T: Options>(tag: Tag) -> impl FnMut(Span<'a>) -> IResult<'a, T> {
+    locate(
+        tag,
+        terminated(
+            assignment(|input| {
+                let (input, _) = keyword("option")(input)?;
+                cut(preceded(ws0, name))(input)
+            }),
+            cut(context("`;`", end_of_declaration(';', true))),
+        ),
+    )
+}
+
+/// Parses a bracketed, comma-separated list of options, such as `[deprecated = true]`.
+///
+/// `tag` is the tag of the options field within the enclosing descriptor.
+pub(super) fn parse_list<'a, T: Options>(tag: Tag) -> impl FnMut(Span<'a>) -> IResult<'a, T> {
+    map(
+        parse_list_with(tag, fail_parser),
+        |(options, _): (Option<T>, Vec<()>)| options.unwrap_or_default(),
+    )
+}
+
+/// Parses a bracketed list of options which may include pseudo-options, such as a field's
+/// `json_name`, which aren't stored in the options message.
+///
+/// Pseudo-options are parsed by `pseudo_option` and returned separately. The options are `None`
+/// if the list contains only pseudo-options.
+pub(super) fn parse_list_with<'a, T, P, F>(
+    tag: Tag,
+    mut pseudo_option: F,
+) -> impl FnMut(Span<'a>) -> IResult<'a, (Option<T>, Vec<P>)>
+where
+    T: Options,
+    F: FnMut(Span<'a>) -> IResult<'a, P>,
+{
+    locate(tag, move |input| {
+        let (mut input, _) = char('[')(input)?;
+        let mut options = None;
+        let mut pseudo_options = Vec::new();
+        loop {
+            let (rest, _) = ws0(input)?;
+            let rest = match pseudo_option(rest) {
+                Ok((rest, parsed)) => {
+                    pseudo_options.push(parsed);
+                    rest
+                }
+                Err(nom::Err::Error(_)) => {
+                    let (rest, parsed) = cut(assignment(name))(rest)?;
+                    merge(&mut options, parsed);
+                    rest
+                }
+                Err(error) => return Err(error),
+            };
+            let (rest, delimiter) =
+                cut(context("`,` or `]`", alt((symbol(','), symbol(']')))))(rest)?;
+            input = rest;
+            if delimiter == ']' {
+                return Ok((input, (options, pseudo_options)));
+            }
+            // Unlike `protoc`, tolerate a trailing comma, which some formatters emit.
+            if let Ok((rest, _)) = symbol(']')(input) {
+                return Ok((rest, (options, pseudo_options)));
+            }
+        }
+    })
+}
+
+/// Parses `name = value`, where the name is parsed by `name`.
+///
+/// The assignment is recorded either as the built-in option it names, or as an uninterpreted
+/// option.
+fn assignment<'a, T, F>(mut name: F) -> impl FnMut(Span<'a>) -> IResult<'a, T>
+where
+    T: Options,
+    F: FnMut(Span<'a>) -> IResult<'a, Vec<NamePart>>,
+{
+    move |input: Span<'a>| {
+        let (_, parts) = peek(&mut name)(input)?;
+        let number = match &parts[..] {
+            [part] if !part.is_extension => T::field_number(&part.name_part),
+            _ => None,
+        };
+        let tag = number.map_or(UNINTERPRETED_OPTION_TAG, Tag::Field);
+
+        let (rest, (parts, value_input, value)) = locate(tag, |input| {
+            let (input, parts) = name(input)?;
+            let (input, _) = cut(context("`=`", symbol('=')))(input)?;
+            let (input, _) = ws0(input)?;
+            let (rest, value) = cut(context("option value", parse_value))(input)?;
+            Ok((rest, (parts, input, value)))
+        })(input)?;
+
+        let mut options = T::default();
+        match number {
+            Some(number) => {
+                if let Err(error) = options.set(number, &value) {
+                    let name = &parts[0].name_part;
+                    return fail(value_input, format!("option \"{}\": {}", name, error));
+                }
+            }
+            None => options
+                .uninterpreted_option()
+                .push(value.into_uninterpreted_option(parts)),
+        }
+        Ok((rest, options))
+    }
+}
+
+/// Parses an option name, such as `java_package`, `(my.option)` or `(my.option).field`.
+fn name(input: Span) -> IResult<Vec<NamePart>> {
+    context(
+        "option name",
+        separated_list1(
+            char('.'),
+            alt((
+                map(
+                    delimited(
+                        char('('),
+                        delimited(ws0, identifier::parse_type, ws0),
+                        cut(char(')')),
+                    ),
+                    |name_part| NamePart {
+                        name_part,
+                        is_extension: true,
+                    },
+                ),
+                map(identifier::parse, |name_part| NamePart {
+                    name_part,
+                    is_extension: false,
+                }),
+            )),
+        ),
+    )(input)
+}
+
+/// Parses an option value.
+fn parse_value(input: Span) -> IResult<Value> {
+    alt((
+        map(aggregate, Value::Aggregate),
+        map(literal::string, Value::String),
+        map(preceded(pair(char('-'), ws0), literal::float), |value| {
+            Value::Double(-value)
+        }),
+        map(literal::float, Value::Double),
+        map_res(preceded(pair(char('-'), ws0), literal::int), |value| {
+            if value <= i64::MAX as u64 + 1 {
+                Ok(Value::NegativeInt((value as i64).wrapping_neg()))
+            } else {
+                Err("integer out of range")
+            }
+        }),
+        map(literal::int, Value::PositiveInt),
+        map_res(
+            preceded(pair(char('-'), ws0), identifier::parse),
+            |ident| match ident.as_str() {
+                "inf" => Ok(Value::Double(f64::NEG_INFINITY)),
+                "nan" => Ok(Value::Double(f64::NAN)),
+                _ => Err("identifier after '-' must be \"inf\" or \"nan\""),
+            },
+        ),
+        map(identifier::parse, Value::Identifier),
+    ))(input)
+}
+
+/// Parses an aggregate value enclosed in braces, such as `{ foo: 1 bar: "baz" }`.
+///
+/// As with `protoc`, the tokens within the braces are joined with single spaces.
+fn aggregate(input: Span) -> IResult<String> {
+    let (mut input, _) = char('{')(input)?;
+    let mut value = String::new();
+    let mut depth = 1;
+    loop {
+        let (rest, _) = ws0(input)?;
+        let (rest, token) = cut(context("`}`", token::any))(rest)?;
+        input = rest;
+        match *token.fragment() {
+            "{" => depth += 1,
+            "}" => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok((input, value));
+                }
+            }
+            _ => (),
+        }
+        if !value.is_empty() {
+            value.push(' ');
+        }
+        value.push_str(token.fragment());
+    }
+}
+
+impl Options for FileOptions {
+    fn field_number(name: &str) -> Option<i32> {
+        Some(match name {
+            "java_package" => 1,
+            "java_outer_classname" => 8,
+            "java_multiple_files" => 10,
+            "java_generate_equals_and_hash" => 20,
+            "java_string_check_utf8" => 27,
+            "go_package" => 11,
+            "cc_generic_services" => 16,
+            "java_generic_services" => 17,
+            "py_generic_services" => 18,
+            "php_generic_services" => 42,
+            "deprecated" => 23,
+            "cc_enable_arenas" => 31,
+            "optimize_for" => 9,
+            "objc_class_prefix" => 36,
+            "csharp_namespace" => 37,
+            "swift_prefix" => 39,
+            "php_class_prefix" => 40,
+            "php_namespace" => 41,
+            "php_metadata_namespace" => 44,
+            "ruby_package" => 45,
+            _ => return None,
+        })
+    }
+
+    fn set(&mut self, number: i32, value: &Value) -> Result<(), String> {
+        match number {
+            1 => self.java_package = Some(value.string()?),
+            8 => self.java_outer_classname = Some(value.string()?),
+            10 => self.java_multiple_files = Some(value.bool()?),
+            #[allow(deprecated)]
+            20 => self.java_generate_equals_and_hash = Some(value.bool()?),
+            27 => self.java_string_check_utf8 = Some(value.bool()?),
+            11 => self.go_package = Some(value.string()?),
+            16 => self.cc_generic_services = Some(value.bool()?),
+            17 => self.java_generic_services = Some(value.bool()?),
+            18 => self.py_generic_services = Some(value.bool()?),
+            42 => self.php_generic_services = Some(value.bool()?),
+            23 => self.deprecated = Some(value.bool()?),
+            31 => self.cc_enable_arenas = Some(value.bool()?),
+            9 => self.optimize_for = Some(value.enumeration(optimize_mode_from_str_name)?),
+            36 => self.objc_class_prefix = Some(value.string()?),
+            37 => self.csharp_namespace = Some(value.string()?),
+            39 => self.swift_prefix = Some(value.string()?),
+            40 => self.php_class_prefix = Some(value.string()?),
+            41 => self.php_namespace = Some(value.string()?),
+            44 => self.php_metadata_namespace = Some(value.string()?),
+            45 => self.ruby_package = Some(value.string()?),
+            _ => unreachable!("unknown file option {}", number),
+        }
+        Ok(())
+    }
+
+    fn uninterpreted_option(&mut self) -> &mut Vec<UninterpretedOption> {
+        &mut self.uninterpreted_option
+    }
+}
+
+fn optimize_mode_from_str_name(name: &str) -> Option<file_options::OptimizeMode> {
+    Some(match name {
+        "SPEED" => file_options::OptimizeMode::Speed,
+        "CODE_SIZE" => file_options::OptimizeMode::CodeSize,
+        "LITE_RUNTIME" => file_options::OptimizeMode::LiteRuntime,
+        _ => return None,
+    })
+}
+
+impl Options for MessageOptions {
+    fn field_number(name: &str) -> Option<i32> {
+        Some(match name {
+            "message_set_wire_format" => 1,
+            "no_standard_descriptor_accessor" => 2,
+            "deprecated" => 3,
+            "map_entry" => 7,
+            _ => return None,
+        })
+    }
+
+    fn set(&mut self, number: i32, value: &Value) -> Result<(), String> {
+        match number {
+            1 => self.message_set_wire_format = Some(value.bool()?),
+            2 => self.no_standard_descriptor_accessor = Some(value.bool()?),
+            3 => self.deprecated = Some(value.bool()?),
+            7 => self.map_entry = Some(value.bool()?),
+            _ => unreachable!("unknown message option {}", number),
+        }
+        Ok(())
+    }
+
+    fn uninterpreted_option(&mut self) -> &mut Vec<UninterpretedOption> {
+        &mut self.uninterpreted_option
+    }
+}
+
+impl Options for FieldOptions {
+    fn field_number(name: &str) -> Option<i32> {
+        Some(match name {
+            "ctype" => 1,
+            "packed" => 2,
+            "deprecated" => 3,
+            "lazy" => 5,
+            "jstype" => 6,
+            "weak" => 10,
+            _ => return None,
+        })
+    }
+
+    fn set(&mut self, number: i32, value: &Value) -> Result<(), String> {
+        match number {
+            1 => self.ctype = Some(value.enumeration(ctype_from_str_name)?),
+            2 => self.packed = Some(value.bool()?),
+            3 => self.deprecated = Some(value.bool()?),
+            5 => self.lazy = Some(value.bool()?),
+            6 => self.jstype = Some(value.enumeration(jstype_from_str_name)?),
+            10 => self.weak = Some(value.bool()?),
+            _ => unreachable!("unknown field option {}", number),
+        }
+        Ok(())
+    }
+
+    fn uninterpreted_option(&mut self) -> &mut Vec<UninterpretedOption> {
+        &mut self.uninterpreted_option
+    }
+}
+
+fn ctype_from_str_name(name: &str) -> Option<field_options::CType> {
+    Some(match name {
+        "STRING" => field_options::CType::String,
+        "CORD" => field_options::CType::Cord,
+        "STRING_PIECE" => field_options::CType::StringPiece,
+        _ => return None,
+    })
+}
+
+fn jstype_from_str_name(name: &str) -> Option<field_options::JsType> {
+    Some(match name {
+        "JS_NORMAL" => field_options::JsType::JsNormal,
+        "JS_STRING" => field_options::JsType::JsString,
+        "JS_NUMBER" => field_options::JsType::JsNumber,
+        _ => return None,
+    })
+}
+
+impl Options for OneofOptions {
+    fn field_number(_: &str) -> Option<i32> {
+        None
+    }
+
+    fn set(&mut self, number: i32, _: &Value) -> Result<(), String> {
+        unreachable!("unknown oneof option {}", number)
+    }
+
+    fn uninterpreted_option(&mut self) -> &mut Vec<UninterpretedOption> {
+        &mut self.uninterpreted_option
+    }
+}
+
+impl Options for EnumOptions {
+    fn field_number(name: &str) -> Option<i32> {
+        Some(match name {
+            "allow_alias" => 2,
+            "deprecated" => 3,
+            _ => return None,
+        })
+    }
+
+    fn set(&mut self, number: i32, value: &Value) -> Result<(), String> {
+        match number {
+            2 => self.allow_alias = Some(value.bool()?),
+            3 => self.deprecated = Some(value.bool()?),
+            _ => unreachable!("unknown enum option {}", number),
+        }
+        Ok(())
+    }
+
+    fn uninterpreted_option(&mut self) -> &mut Vec<UninterpretedOption> {
+        &mut self.uninterpreted_option
+    }
+}
+
+impl Options for EnumValueOptions {
+    fn field_number(name: &str) -> Option<i32> {
+        Some(match name {
+            "deprecated" => 1,
+            _ => return None,
+        })
+    }
+
+    fn set(&mut self, number: i32, value: &Value) -> Result<(), String> {
+        match number {
+            1 => self.deprecated = Some(value.bool()?),
+            _ => unreachable!("unknown enum value option {}", number),
+        }
+        Ok(())
+    }
+
+    fn uninterpreted_option(&mut self) -> &mut Vec<UninterpretedOption> {
+        &mut self.uninterpreted_option
+    }
+}
+
+impl Options for ExtensionRangeOptions {
+    fn field_number(_: &str) -> Option<i32> {
+        None
+    }
+
+    fn set(&mut self, number: i32, _: &Value) -> Result<(), String> {
+        unreachable!("unknown extension range option {}", number)
+    }
+
+    fn uninterpreted_option(&mut self) -> &mut Vec<UninterpretedOption> {
+        &mut self.uninterpreted_option
+    }
+}
+
+impl Options for ServiceOptions {
+    fn field_number(name: &str) -> Option<i32> {
+        Some(match name {
+            "deprecated" => 33,
+            _ => return None,
+        })
+    }
+
+    fn set(&mut self, number: i32, value: &Value) -> Result<(), String> {
+        match number {
+            33 => self.deprecated = Some(value.bool()?),
+            _ => unreachable!("unknown service option {}", number),
+        }
+        Ok(())
+    }
+
+    fn uninterpreted_option(&mut self) -> &mut Vec<UninterpretedOption> {
+        &mut self.uninterpreted_option
+    }
+}
+
+impl Options for MethodOptions {
+    fn field_number(name: &str) -> Option<i32> {
+        Some(match name {
+            "deprecated" => 33,
+            "idempotency_level" => 34,
+            _ => return None,
+        })
+    }
+
+    fn set(&mut self, number: i32, value: &Value) -> Result<(), String> {
+        match number {
+            33 => self.deprecated = Some(value.bool()?),
+            34 => {
+                self.idempotency_level = Some(value.enumeration(idempotency_level_from_str_name)?)
+            }
+            _ => unreachable!("unknown method option {}", number),
+        }
+        Ok(())
+    }
+
+    fn uninterpreted_option(&mut self) -> &mut Vec<UninterpretedOption> {
+        &mut self.uninterpreted_option
+    }
+}
+
+fn idempotency_level_from_str_name(name: &str) -> Option<method_options::IdempotencyLevel> {
+    Some(match name {
+        "IDEMPOTENCY_UNKNOWN" => method_options::IdempotencyLevel::IdempotencyUnknown,
+        "NO_SIDE_EFFECTS" => method_options::IdempotencyLevel::NoSideEffects,
+        "IDEMPOTENT" => method_options::IdempotencyLevel::Idempotent,
+        _ => return None,
+    })
+}
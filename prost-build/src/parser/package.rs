@@ -0,0 +1,25 @@
+use nom::combinator::cut;
+use nom::error::context;
+use nom::sequence::{preceded, terminated};
+
+use super::identifier;
+use super::location::{end_of_declaration, locate, Tag};
+use super::token::{keyword, ws0};
+use super::{IResult, Span};
+
+/// `FileDescriptorProto.package`
+pub(super) const TAG: Tag = Tag::Field(2);
+
+/// Parses a `package foo.bar;` statement.
+pub(super) fn parse(input: Span) -> IResult<String> {
+    locate(
+        TAG,
+        preceded(
+            keyword("package"),
+            cut(terminated(
+                preceded(ws0, context("package name", identifier::parse_full)),
+                context("`;`", end_of_declaration(';', true)),
+            )),
+        ),
+    )(input)
+}
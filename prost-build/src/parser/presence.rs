@@ -0,0 +1,90 @@
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::FieldDescriptorProto;
+
+use super::Syntax;
+
+/// Whether a field tracks if it has been set, as opposed to just holding its default value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Presence {
+    /// The field distinguishes being unset from being set to its default value, such as a proto2
+    /// `optional` field or a message field.
+    Explicit,
+    /// The field is considered unset whenever it holds its default value, as with proto3 scalar
+    /// fields and repeated fields.
+    Implicit,
+}
+
+/// Returns the presence semantics of `field`, declared in a file with the given syntax.
+///
+/// Fields referring to messages or enums must have been resolved, so that their `type` is set.
+pub fn field_presence(field: &FieldDescriptorProto, syntax: Syntax) -> Presence {
+    if field.label() == Label::Repeated {
+        return Presence::Implicit;
+    }
+    match syntax {
+        Syntax::Proto2 => Presence::Explicit,
+        Syntax::Proto3 => {
+            // Members of a oneof, including proto3 `optional` fields, always track presence.
+            if field.oneof_index.is_some() || field.r#type() == Type::Message {
+                Presence::Explicit
+            } else {
+                Presence::Implicit
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_field(label: Label, r#type: Type) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            label: Some(label as i32),
+            r#type: Some(r#type as i32),
+            ..FieldDescriptorProto::default()
+        }
+    }
+
+    #[test]
+    fn proto3_scalar_is_implicit() {
+        let field = new_field(Label::Optional, Type::Int32);
+        assert_eq!(field_presence(&field, Syntax::Proto3), Presence::Implicit);
+
+        let field = new_field(Label::Optional, Type::Enum);
+        assert_eq!(field_presence(&field, Syntax::Proto3), Presence::Implicit);
+    }
+
+    #[test]
+    fn proto3_optional_is_explicit() {
+        let field = FieldDescriptorProto {
+            oneof_index: Some(0),
+            proto3_optional: Some(true),
+            ..new_field(Label::Optional, Type::String)
+        };
+        assert_eq!(field_presence(&field, Syntax::Proto3), Presence::Explicit);
+    }
+
+    #[test]
+    fn message_is_explicit() {
+        let field = new_field(Label::Optional, Type::Message);
+        assert_eq!(field_presence(&field, Syntax::Proto3), Presence::Explicit);
+        assert_eq!(field_presence(&field, Syntax::Proto2), Presence::Explicit);
+    }
+
+    #[test]
+    fn proto2_singular_is_explicit() {
+        let field = new_field(Label::Optional, Type::Int32);
+        assert_eq!(field_presence(&field, Syntax::Proto2), Presence::Explicit);
+
+        let field = new_field(Label::Required, Type::Bytes);
+        assert_eq!(field_presence(&field, Syntax::Proto2), Presence::Explicit);
+    }
+
+    #[test]
+    fn repeated_is_implicit() {
+        let field = new_field(Label::Repeated, Type::Message);
+        assert_eq!(field_presence(&field, Syntax::Proto2), Presence::Implicit);
+        assert_eq!(field_presence(&field, Syntax::Proto3), Presence::Implicit);
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+use std::mem;
+
+use prost_types::{DescriptorProto, FileDescriptorProto, FileDescriptorSet};
+
+use super::lookup::relative_name;
+
+/// `FileDescriptorProto.message_type`
+const MESSAGE_TAG: i32 = 4;
+/// `FileDescriptorProto.enum_type`
+const ENUM_TAG: i32 = 5;
+/// `FileDescriptorProto.extension`
+const EXTENSION_TAG: i32 = 7;
+
+/// A top-level message or enum: the index of its file, the field of `FileDescriptorProto` which
+/// holds it, and its index within that field.
+type Element = (usize, i32, usize);
+
+/// Returns a copy of the resolved `descriptors` which keeps only services and the message and
+/// enum types they refer to, directly or indirectly.
+///
+/// Types are kept or dropped along with the top-level type enclosing them, so a kept message
+/// keeps all of its nested types. Top-level extensions are dropped. Every file is kept, even if
+/// nothing is left in it, and the locations of the remaining definitions are renumbered to match.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::path::PathBuf;
+///
+/// let mut files = HashMap::new();
+/// files.insert(
+///     PathBuf::from("greeter.proto"),
+///     r#"syntax = "proto3";
+///     message Request {} message Response {} message Unused {}
+///     service Greeter { rpc Greet(Request) returns (Response); }"#
+///         .to_string(),
+/// );
+/// let descriptors = prost_build::parser::parse(&files).unwrap();
+///
+/// let pruned = prost_build::parser::prune_to_services(&descriptors);
+/// let names: Vec<_> = pruned.file[0].message_type.iter().map(|m| m.name()).collect();
+/// assert_eq!(names, ["Request", "Response"]);
+/// ```
+pub fn prune_to_services(descriptors: &FileDescriptorSet) -> FileDescriptorSet {
+    let mut names: Vec<&str> = descriptors
+        .file
+        .iter()
+        .flat_map(|file| &file.service)
+        .flat_map(|service| &service.method)
+        .flat_map(|method| vec![method.input_type(), method.output_type()])
+        .collect();
+
+    let mut kept = HashSet::new();
+    while let Some(name) = names.pop() {
+        let element = match find(descriptors, name) {
+            Some(element) => element,
+            None => continue,
+        };
+        if !kept.insert(element) {
+            continue;
+        }
+        let (file, tag, i) = element;
+        if tag == MESSAGE_TAG {
+            references(&descriptors.file[file].message_type[i], &mut names);
+        }
+    }
+
+    let file = descriptors
+        .file
+        .iter()
+        .enumerate()
+        .map(|(i, file)| prune_file(file, |tag, j| kept.contains(&(i, tag, j))))
+        .collect();
+    FileDescriptorSet { file }
+}
+
+/// Finds the top-level type which is, or encloses, the type with the given fully-qualified name.
+fn find(descriptors: &FileDescriptorSet, name: &str) -> Option<Element> {
+    let full_name = name.strip_prefix('.')?;
+    descriptors.file.iter().enumerate().find_map(|(i, file)| {
+        let name = relative_name(file, full_name)?;
+        let first = name.split('.').next()?;
+        if let Some(j) = file.message_type.iter().position(|m| m.name() == first) {
+            Some((i, MESSAGE_TAG, j))
+        } else {
+            let j = file.enum_type.iter().position(|e| e.name() == first)?;
+            Some((i, ENUM_TAG, j))
+        }
+    })
+}
+
+/// Appends the names of every type referred to by `message` and its nested types to `names`.
+fn references<'a>(message: &'a DescriptorProto, names: &mut Vec<&'a str>) {
+    for field in message.field.iter().chain(&message.extension) {
+        names.extend(field.type_name.as_deref());
+        names.extend(field.extendee.as_deref());
+    }
+    for nested in &message.nested_type {
+        references(nested, names);
+    }
+}
+
+/// Copies `file`, keeping the top-level types for which `keep` returns `true`.
+fn prune_file(
+    file: &FileDescriptorProto,
+    keep: impl Fn(i32, usize) -> bool,
+) -> FileDescriptorProto {
+    // The new index of each top-level message and enum, or `None` if it's dropped.
+    let renumber = |tag, len| {
+        let mut next = 0;
+        (0..len)
+            .map(|i| {
+                keep(tag, i).then(|| {
+                    next += 1;
+                    next - 1
+                })
+            })
+            .collect::<Vec<Option<i32>>>()
+    };
+    let messages = renumber(MESSAGE_TAG, file.message_type.len());
+    let enums = renumber(ENUM_TAG, file.enum_type.len());
+
+    let mut pruned = file.clone();
+    pruned.message_type = retain(&file.message_type, &messages);
+    pruned.enum_type = retain(&file.enum_type, &enums);
+    pruned.extension.clear();
+    if let Some(info) = &mut pruned.source_code_info {
+        info.location = mem::take(&mut info.location)
+            .into_iter()
+            .filter_map(|mut location| {
+                match location.path[..] {
+                    [EXTENSION_TAG, ..] => return None,
+                    [tag @ (MESSAGE_TAG | ENUM_TAG), i, ..] => {
+                        let indices = if tag == MESSAGE_TAG {
+                            &messages
+                        } else {
+                            &enums
+                        };
+                        location.path[1] = indices[i as usize]?;
+                    }
+                    _ => (),
+                }
+                Some(location)
+            })
+            .collect();
+    }
+    pruned
+}
+
+/// Clones the elements of `elements` which have a new index.
+fn retain<T: Clone>(elements: &[T], indices: &[Option<i32>]) -> Vec<T> {
+    elements
+        .iter()
+        .zip(indices)
+        .filter(|(_, index)| index.is_some())
+        .map(|(element, _)| element.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn prunes_types_not_used_by_services() {
+        let files = HashMap::from([
+            (
+                PathBuf::from("service.proto"),
+                r#"syntax = "proto2";
+package foo;
+import "types.proto";
+message Unused { optional Status status = 1; }
+// The request.
+message Request {
+  optional Item item = 1;
+  extensions 100 to 199;
+}
+extend Request { optional Unused unused = 100; }
+service Items { rpc Get(Request) returns (Item); }
+"#
+                .to_owned(),
+            ),
+            (
+                PathBuf::from("types.proto"),
+                r#"syntax = "proto2";
+package foo;
+enum Status { OK = 0; }
+message Item {
+  optional Outer.Kind kind = 1;
+}
+message Outer { enum Kind { A = 0; } }
+message Other {}
+"#
+                .to_owned(),
+            ),
+        ]);
+        let descriptors = parse(&files).unwrap();
+        let pruned = prune_to_services(&descriptors);
+
+        fn names(file: &FileDescriptorProto) -> Vec<&str> {
+            let messages = file.message_type.iter().map(|message| message.name());
+            let enums = file.enum_type.iter().map(|r#enum| r#enum.name());
+            messages.chain(enums).collect()
+        }
+        assert_eq!(pruned.file[0].name(), "types.proto");
+        assert_eq!(names(&pruned.file[0]), ["Item", "Outer"]);
+        assert_eq!(pruned.file[1].name(), "service.proto");
+        assert_eq!(names(&pruned.file[1]), ["Request"]);
+        assert!(pruned.file[1].extension.is_empty());
+        assert_eq!(pruned.file[1].service, descriptors.file[1].service);
+
+        let locations = &pruned.file[1].source_code_info.as_ref().unwrap().location;
+        let request = locations
+            .iter()
+            .find(|location| location.path == [4, 0])
+            .unwrap();
+        assert_eq!(request.leading_comments(), " The request.\n");
+        assert!(locations.iter().all(
+            |location| !location.path.starts_with(&[4, 1]) && !location.path.starts_with(&[7])
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+use nom::branch::alt;
+use nom::combinator::{cut, map, map_res, opt};
+use nom::error::context;
+use nom::multi::separated_list1;
+use nom::sequence::{pair, preceded};
+
+use super::error::fail;
+use super::location::{end_of_declaration, locate, Tag};
+use super::token::{keyword, symbol, ws0};
+use super::{literal, IResult, Span};
+
+/// The largest field number.
+pub(super) const MAX_FIELD_NUMBER: i32 = 536_870_911;
+
+/// The contents of a `reserved` statement.
+#[derive(Debug, PartialEq)]
+pub(super) enum Reserved {
+    /// Inclusive ranges of reserved numbers.
+    Ranges(Vec<(i32, i32)>),
+    Names(Vec<String>),
+}
+
+/// Parses a `reserved` statement within a message.
+pub(super) fn parse_message(input: Span) -> IResult<Reserved> {
+    // `DescriptorProto.reserved_range` and `DescriptorProto.reserved_name`
+    parse(Tag::Field(9), Tag::Field(10), MAX_FIELD_NUMBER)(input)
+}
+
+/// Parses a `reserved` statement within an enum.
+pub(super) fn parse_enum(input: Span) -> IResult<Reserved> {
+    // `EnumDescriptorProto.reserved_range` and `EnumDescriptorProto.reserved_name`
+    parse(Tag::Field(4), Tag::Field(5), i32::MAX)(input)
+}
+
+fn parse<'a>(
+    ranges_tag: Tag,
+    names_tag: Tag,
+    max: i32,
+) -> impl FnMut(Span<'a>) -> IResult<'a, Reserved> {
+    move |input: Span<'a>| {
+        // Reserved names must be quoted, so distinguish them from numbers by their first
+        // character, and report bare identifiers rather than failing to parse them as numbers.
+        let (rest, ()) = preceded(keyword("reserved"), ws0)(input)?;
+        let first = rest.fragment().chars().next();
+        if matches!(first, Some(c) if c.is_ascii_alphabetic() || c == '_') {
+            return fail(
+                rest,
+                "reserved names must be quoted strings, e.g. `reserved \"foo\";`",
+            );
+        }
+
+        if matches!(first, Some('"' | '\'')) {
+            locate(
+                names_tag,
+                preceded(
+                    keyword("reserved"),
+                    cut(map(
+                        pair(
+                            separated_list1(
+                                symbol(','),
+                                preceded(
+                                    ws0,
+                                    locate(
+                                        Tag::Index,
+                                        context(
+                                            "string",
+                                            map_res(literal::string, String::from_utf8),
+                                        ),
+                                    ),
+                                ),
+                            ),
+                            context("`;`", end_of_declaration(';', true)),
+                        ),
+                        |(names, ())| Reserved::Names(names),
+                    )),
+                ),
+            )(input)
+        } else {
+            locate(
+                ranges_tag,
+                preceded(
+                    keyword("reserved"),
+                    cut(map(
+                        pair(
+                            separated_list1(symbol(','), preceded(ws0, range(max))),
+                            context("`;`", end_of_declaration(';', true)),
+                        ),
+                        |(ranges, ())| Reserved::Ranges(ranges),
+                    )),
+                ),
+            )(input)
+        }
+    }
+}
+
+/// Parses a range such as `5`, `1 to 10` or `100 to max`, returning the inclusive bounds.
+///
+/// Each range is located within the location of the enclosing statement.
+pub(super) fn range<'a>(max: i32) -> impl FnMut(Span<'a>) -> IResult<'a, (i32, i32)> {
+    locate(Tag::Index, move |input: Span<'a>| {
+        let (input, start) = literal::int32(input)?;
+        let (input, end) = opt(preceded(
+            preceded(ws0, keyword("to")),
+            cut(preceded(
+                ws0,
+                alt((map(keyword("max"), |_| max), literal::int32)),
+            )),
+        ))(input)?;
+        Ok((input, (start, end.unwrap_or(start))))
+    })
+}
@@ -0,0 +1,474 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use prost_types::field_descriptor_proto::Type;
+use prost_types::{
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+};
+
+use super::{field, token, ResolutionError};
+
+/// The kind of a named element of a descriptor set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Package,
+    Message,
+    Enum,
+    EnumValue,
+    Service,
+}
+
+impl Kind {
+    fn is_type(self) -> bool {
+        matches!(self, Kind::Message | Kind::Enum)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Symbol {
+    kind: Kind,
+    /// The file defining the symbol, or for packages, which may span several files, the first
+    /// file declaring it.
+    file: String,
+}
+
+/// The fully-qualified names of every symbol defined by a set of files.
+#[derive(Debug, Default)]
+struct Symbols {
+    symbols: HashMap<String, Symbol>,
+    /// The value names of each enum, keyed by the enum's fully-qualified name.
+    enum_values: HashMap<String, HashSet<String>>,
+}
+
+impl Symbols {
+    fn new(files: &[FileDescriptorProto]) -> Result<Symbols, ResolutionError> {
+        let mut symbols = Symbols::default();
+        for file in files {
+            let mut scope = String::new();
+            for part in file.package().split('.').filter(|part| !part.is_empty()) {
+                scope.push('.');
+                scope.push_str(part);
+                // `FileDescriptorProto.package`
+                symbols.add(file, &[2], scope.clone(), Kind::Package)?;
+            }
+            for (i, message) in file.message_type.iter().enumerate() {
+                symbols.add_message(file, &[4, i as i32], &scope, message)?;
+            }
+            for (i, r#enum) in file.enum_type.iter().enumerate() {
+                symbols.add_enum(file, &[5, i as i32], &scope, r#enum)?;
+            }
+            for (i, service) in file.service.iter().enumerate() {
+                let full_name = format!("{}.{}", scope, service.name());
+                symbols.add(file, &[6, i as i32], full_name, Kind::Service)?;
+            }
+        }
+        Ok(symbols)
+    }
+
+    /// Adds `message` and the types nested within it, where `path` is the message's location
+    /// path within `file`.
+    fn add_message(
+        &mut self,
+        file: &FileDescriptorProto,
+        path: &[i32],
+        scope: &str,
+        message: &DescriptorProto,
+    ) -> Result<(), ResolutionError> {
+        let full_name = format!("{}.{}", scope, message.name());
+        for (i, nested) in message.nested_type.iter().enumerate() {
+            self.add_message(file, &[path, &[3, i as i32]].concat(), &full_name, nested)?;
+        }
+        for (i, r#enum) in message.enum_type.iter().enumerate() {
+            self.add_enum(file, &[path, &[4, i as i32]].concat(), &full_name, r#enum)?;
+        }
+        self.add(file, path, full_name, Kind::Message)
+    }
+
+    fn add_enum(
+        &mut self,
+        file: &FileDescriptorProto,
+        path: &[i32],
+        scope: &str,
+        r#enum: &EnumDescriptorProto,
+    ) -> Result<(), ResolutionError> {
+        let full_name = format!("{}.{}", scope, r#enum.name());
+        let values = r#enum.value.iter().map(|value| value.name().to_owned());
+        self.enum_values.insert(full_name.clone(), values.collect());
+        self.add(file, path, full_name, Kind::Enum)?;
+        // As in C++, enum values are siblings of their enum rather than children of it.
+        for (i, value) in r#enum.value.iter().enumerate() {
+            let value_name = format!("{}.{}", scope, value.name());
+            self.add(
+                file,
+                &[path, &[2, i as i32]].concat(),
+                value_name,
+                Kind::EnumValue,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Adds the symbol `full_name`, declared at `path` within `file`, reporting an error at the
+    /// declaration if the name is already taken.
+    fn add(
+        &mut self,
+        file: &FileDescriptorProto,
+        path: &[i32],
+        full_name: String,
+        kind: Kind,
+    ) -> Result<(), ResolutionError> {
+        let symbol = Symbol {
+            kind,
+            file: file.name().to_owned(),
+        };
+        match self.symbols.get(&full_name) {
+            Some(existing) if existing.kind == Kind::Package && kind == Kind::Package => Ok(()),
+            Some(existing) => {
+                let mut message = format!(
+                    "\"{}\" is already defined in \"{}\"",
+                    &full_name[1..],
+                    existing.file,
+                );
+                if kind == Kind::EnumValue {
+                    message.push_str(
+                        "; enum values are siblings of their enum, so their names must be unique within the enclosing scope",
+                    );
+                }
+                Err(ResolutionError::at(file, path, message))
+            }
+            None => {
+                self.symbols.insert(full_name, symbol);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Resolves type references in a set of parsed files.
+///
+/// Every field and method type is replaced with its fully-qualified name, and the type of each
+/// field referring to a message or enum is set accordingly.
+pub(super) fn resolve(files: &mut [FileDescriptorProto]) -> Result<(), ResolutionError> {
+    let symbols = Symbols::new(files)?;
+    let public: HashMap<String, Vec<String>> = files
+        .iter()
+        .map(|file| {
+            let dependencies = file
+                .public_dependency
+                .iter()
+                .map(|&index| file.dependency[index as usize].clone())
+                .collect();
+            (file.name().to_owned(), dependencies)
+        })
+        .collect();
+
+    for file in files.iter_mut() {
+        let visible = visible_files(file, &public);
+        let resolver = Resolver {
+            symbols: &symbols,
+            visible,
+        };
+        let error_at = |path: &[i32], message| ResolutionError::at(file, path, message);
+        let package = match file.package() {
+            "" => String::new(),
+            package => format!(".{}", package),
+        };
+
+        let mut messages = file.message_type.clone();
+        for (i, message) in messages.iter_mut().enumerate() {
+            resolver.resolve_message(&package, message, &mut vec![4, i as i32], &error_at)?;
+        }
+        let mut extensions = file.extension.clone();
+        for (i, field) in extensions.iter_mut().enumerate() {
+            resolver.resolve_field(&package, field, &[7, i as i32], &error_at)?;
+        }
+        let mut services = file.service.clone();
+        for (i, service) in services.iter_mut().enumerate() {
+            let scope = format!("{}.{}", package, service.name());
+            for (j, method) in service.method.iter_mut().enumerate() {
+                let path = [6, i as i32, 2, j as i32];
+                for type_name in [&mut method.input_type, &mut method.output_type] {
+                    let name = type_name.as_deref().unwrap_or_default();
+                    match resolver.lookup(&scope, name) {
+                        Ok((full_name, Kind::Message)) => *type_name = Some(full_name),
+                        Ok(_) => {
+                            return Err(error_at(
+                                &path,
+                                format!("\"{}\" is not a message type", name),
+                            ))
+                        }
+                        Err(error) => return Err(error_at(&path, error)),
+                    }
+                }
+            }
+        }
+        file.message_type = messages;
+        file.extension = extensions;
+        file.service = services;
+    }
+    Ok(())
+}
+
+/// Returns the names of the files whose symbols are visible from `file`: the file itself, the
+/// files it imports, and the files those publicly import, transitively.
+///
+/// `public` maps the name of each file to the names of the files it publicly imports.
+fn visible_files<'a>(
+    file: &'a FileDescriptorProto,
+    public: &'a HashMap<String, Vec<String>>,
+) -> HashSet<&'a str> {
+    let mut visible = HashSet::new();
+    visible.insert(file.name());
+    let mut pending: Vec<&str> = file.dependency.iter().map(String::as_str).collect();
+    while let Some(name) = pending.pop() {
+        if visible.insert(name) {
+            pending.extend(public.get(name).into_iter().flatten().map(String::as_str));
+        }
+    }
+    visible
+}
+
+struct Resolver<'a> {
+    symbols: &'a Symbols,
+    /// The files whose symbols are visible from the file being resolved.
+    visible: HashSet<&'a str>,
+}
+
+impl<'a> Resolver<'a> {
+    fn resolve_message(
+        &self,
+        scope: &str,
+        message: &mut DescriptorProto,
+        path: &mut Vec<i32>,
+        error_at: &impl Fn(&[i32], String) -> ResolutionError,
+    ) -> Result<(), ResolutionError> {
+        let full_name = format!("{}.{}", scope, message.name());
+        for (i, field) in message.field.iter_mut().enumerate() {
+            path.extend([2, i as i32]);
+            self.resolve_field(&full_name, field, path, error_at)?;
+            path.truncate(path.len() - 2);
+        }
+        for (i, field) in message.extension.iter_mut().enumerate() {
+            path.extend([6, i as i32]);
+            self.resolve_field(&full_name, field, path, error_at)?;
+            path.truncate(path.len() - 2);
+        }
+        for (i, nested) in message.nested_type.iter_mut().enumerate() {
+            path.extend([3, i as i32]);
+            self.resolve_message(&full_name, nested, path, error_at)?;
+            path.truncate(path.len() - 2);
+        }
+        Ok(())
+    }
+
+    /// Resolves the type of `field`, and its extendee if it's an extension, declared within
+    /// `scope`.
+    fn resolve_field(
+        &self,
+        scope: &str,
+        field: &mut FieldDescriptorProto,
+        path: &[i32],
+        error_at: &impl Fn(&[i32], String) -> ResolutionError,
+    ) -> Result<(), ResolutionError> {
+        if let Some(extendee) = &field.extendee {
+            match self.lookup(scope, extendee) {
+                Ok((full_name, Kind::Message)) => field.extendee = Some(full_name),
+                Ok(_) => {
+                    return Err(error_at(
+                        path,
+                        format!("\"{}\" is not a message type", extendee),
+                    ))
+                }
+                Err(error) => return Err(error_at(path, error)),
+            }
+        }
+
+        let type_name = match &field.type_name {
+            Some(type_name) if field.r#type.is_none() || field.r#type() == Type::Group => type_name,
+            _ => return Ok(()),
+        };
+        let (resolved, kind) = self
+            .lookup(scope, type_name)
+            .map_err(|error| error_at(path, error))?;
+        if let Some(default) = &field.default_value {
+            match kind {
+                Kind::Message => {
+                    return Err(error_at(
+                        path,
+                        "messages can't have default values".to_owned(),
+                    ))
+                }
+                // Enum defaults are kept as written, since the type isn't known while parsing.
+                Kind::Enum
+                    if !default.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                        || !default.chars().all(token::is_ident_char) =>
+                {
+                    return Err(error_at(
+                        path,
+                        "enum defaults must be identifiers".to_owned(),
+                    ))
+                }
+                Kind::Enum if !self.symbols.enum_values[&resolved].contains(default) => {
+                    return Err(error_at(
+                        path,
+                        format!(
+                            "enum type \"{}\" has no value named \"{}\"",
+                            &resolved[1..],
+                            default
+                        ),
+                    ))
+                }
+                _ => (),
+            }
+        }
+
+        let r#type = match kind {
+            Kind::Message if field.r#type() == Type::Group => Type::Group,
+            Kind::Message => Type::Message,
+            Kind::Enum => Type::Enum,
+            Kind::Package | Kind::EnumValue | Kind::Service => {
+                unreachable!("lookup returned a non-type")
+            }
+        };
+        field.r#type = Some(r#type as i32);
+        field.type_name = Some(resolved);
+        Ok(())
+    }
+
+    /// Looks up the type `name` referenced from within `scope`, in the same way as `protoc`.
+    ///
+    /// Relative names are searched for in each enclosing scope, from innermost to outermost.
+    /// Only the first component of the name is matched in each scope; once it matches, the rest
+    /// of the name must be found within it.
+    fn lookup(&self, scope: &str, name: &str) -> Result<(String, Kind), String> {
+        if let Some(full_name) = name.strip_prefix('.') {
+            return match self.find(name) {
+                Some(kind) if kind.is_type() => Ok((name.to_owned(), kind)),
+                _ => Err(format!("\"{}\" is not defined", full_name)),
+            };
+        }
+
+        let first = name.split('.').next().unwrap_or_default();
+        let mut scope = scope.to_owned();
+        loop {
+            let candidate = format!("{}.{}", scope, first);
+            if let Some(kind) = self.find(&candidate) {
+                if first.len() < name.len() && kind != Kind::EnumValue {
+                    // Every kind of symbol but an enum value may contain other symbols, so the
+                    // rest of the name must be within this one.
+                    let full_name = format!("{}.{}", scope, name);
+                    return match self.find(&full_name) {
+                        Some(kind) if kind.is_type() => Ok((full_name, kind)),
+                        _ => Err(format!("\"{}\" is not defined", name)),
+                    };
+                } else if kind.is_type() {
+                    return Ok((candidate, kind));
+                }
+            }
+
+            match scope.rfind('.') {
+                Some(dot) => scope.truncate(dot),
+                None => {
+                    return Err(match field::similar_scalar(name) {
+                        Some(scalar) => {
+                            format!("\"{}\" is not defined; did you mean \"{}\"?", name, scalar)
+                        }
+                        None => format!("\"{}\" is not defined", name),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Returns the kind of the symbol with the given fully-qualified name, if it's visible.
+    fn find(&self, full_name: &str) -> Option<Kind> {
+        let symbol = self.symbols.symbols.get(full_name)?;
+        match symbol.kind {
+            Kind::Package => Some(symbol.kind),
+            _ if !self.visible.contains(symbol.file.as_str()) => None,
+            _ => Some(symbol.kind),
+        }
+    }
+}
+
+/// Sorts files so that every file follows its dependencies.
+///
+/// Files which don't depend on each other are sorted by name, so the order is stable. Imports of
+/// files which aren't in the set are ignored.
+pub(super) fn sort(
+    files: Vec<FileDescriptorProto>,
+) -> Result<Vec<FileDescriptorProto>, ResolutionError> {
+    let by_name = files_by_name(&files);
+    let mut sorted = Vec::with_capacity(files.len());
+    let mut visited = HashSet::new();
+    let mut stack = Vec::new();
+    for name in by_name.keys() {
+        visit(name, &by_name, &mut visited, &mut stack, &mut sorted)?;
+    }
+
+    let mut files: HashMap<String, FileDescriptorProto> = files
+        .into_iter()
+        .map(|file| (file.name().to_owned(), file))
+        .collect();
+    Ok(sorted
+        .into_iter()
+        .map(|name| files.remove(&name).expect("sorted file is missing"))
+        .collect())
+}
+
+/// Returns the names of the files which the file `name` imports, directly or indirectly, with
+/// every file following its dependencies.
+///
+/// As with [`sort`], imports of files which aren't in `files` are ignored.
+pub(super) fn dependencies(
+    files: &[FileDescriptorProto],
+    name: &str,
+) -> Result<Vec<String>, ResolutionError> {
+    let by_name = files_by_name(files);
+    let mut sorted = Vec::new();
+    visit(
+        name,
+        &by_name,
+        &mut HashSet::new(),
+        &mut Vec::new(),
+        &mut sorted,
+    )?;
+    // The file itself is visited last.
+    sorted.pop();
+    Ok(sorted)
+}
+
+fn files_by_name(files: &[FileDescriptorProto]) -> BTreeMap<&str, &FileDescriptorProto> {
+    files.iter().map(|file| (file.name(), file)).collect()
+}
+
+fn visit(
+    name: &str,
+    files: &BTreeMap<&str, &FileDescriptorProto>,
+    visited: &mut HashSet<String>,
+    stack: &mut Vec<String>,
+    sorted: &mut Vec<String>,
+) -> Result<(), ResolutionError> {
+    let file = match files.get(name) {
+        Some(file) if !visited.contains(name) => file,
+        _ => return Ok(()),
+    };
+
+    stack.push(name.to_owned());
+    for (index, dependency) in file.dependency.iter().enumerate() {
+        if let Some(start) = stack.iter().position(|entry| entry == dependency) {
+            let mut cycle = stack[start..].to_vec();
+            cycle.push(dependency.clone());
+            return Err(ResolutionError::at(
+                file,
+                &[3, index as i32],
+                format!("file recursively imports itself: {}", cycle.join(" -> ")),
+            ));
+        }
+        visit(dependency, files, visited, stack, sorted)?;
+    }
+    stack.pop();
+
+    visited.insert(name.to_owned());
+    sorted.push(name.to_owned());
+    Ok(())
+}
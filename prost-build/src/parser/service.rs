@@ -0,0 +1,61 @@
+use nom::branch::alt;
+use nom::combinator::{cut, map};
+use nom::error::context;
+use nom::multi::many0;
+use nom::sequence::preceded;
+use prost_types::{MethodDescriptorProto, ServiceDescriptorProto, ServiceOptions};
+
+use super::location::{end_of_declaration, locate, Tag};
+use super::token::{keyword, ws0};
+use super::{identifier, method, option, IResult, Span};
+
+/// `FileDescriptorProto.service`
+pub(super) const FILE_TAG: Tag = Tag::Repeated(6);
+/// `ServiceDescriptorProto.options`
+const OPTIONS_TAG: Tag = Tag::Field(3);
+
+enum Statement {
+    Option(ServiceOptions),
+    Method(MethodDescriptorProto),
+    Empty,
+}
+
+/// Parses a `service` definition, which is stored in the enclosing descriptor's field `tag`.
+pub(super) fn parse<'a>(tag: Tag) -> impl FnMut(Span<'a>) -> IResult<'a, ServiceDescriptorProto> {
+    locate(
+        tag,
+        preceded(
+            keyword("service"),
+            cut(|input| {
+                let (input, name) = preceded(ws0, identifier::parse)(input)?;
+                let (input, _) = context("`{`", end_of_declaration('{', true))(input)?;
+                let (input, statements) = many0(statement)(input)?;
+                let (input, _) = context("`}`", end_of_declaration('}', false))(input)?;
+
+                let mut service = ServiceDescriptorProto {
+                    name: Some(name),
+                    ..ServiceDescriptorProto::default()
+                };
+                for statement in statements {
+                    match statement {
+                        Statement::Option(options) => option::merge(&mut service.options, options),
+                        Statement::Method(method) => service.method.push(method),
+                        Statement::Empty => (),
+                    }
+                }
+                Ok((input, service))
+            }),
+        ),
+    )
+}
+
+fn statement(input: Span) -> IResult<Statement> {
+    alt((
+        map(
+            option::parse_statement::<ServiceOptions>(OPTIONS_TAG),
+            Statement::Option,
+        ),
+        map(method::parse, Statement::Method),
+        map(end_of_declaration(';', false), |()| Statement::Empty),
+    ))(input)
+}
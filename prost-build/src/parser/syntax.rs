@@ -0,0 +1,77 @@
+use std::fmt;
+
+use nom::combinator::cut;
+use nom::error::context;
+
+use super::error::fail;
+use super::literal;
+use super::location::{end_of_declaration, locate, Tag};
+use super::token::{keyword, symbol, ws0};
+use super::{IResult, Span};
+
+/// `FileDescriptorProto.syntax`
+const TAG: Tag = Tag::Field(12);
+
+/// The syntax of a `.proto` file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Syntax {
+    Proto2,
+    Proto3,
+}
+
+impl Default for Syntax {
+    fn default() -> Syntax {
+        Syntax::Proto2
+    }
+}
+
+impl Syntax {
+    /// Returns the name of the syntax, as used in the `syntax` statement.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Syntax::Proto2 => "proto2",
+            Syntax::Proto3 => "proto3",
+        }
+    }
+}
+
+impl fmt::Display for Syntax {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses a `syntax = "...";` statement, and records the syntax in the parse state.
+///
+/// Files declaring an `edition` instead are rejected, since editions can't be represented by the
+/// descriptors of this version of `prost-types`.
+pub(super) fn parse(input: Span) -> IResult<Syntax> {
+    if keyword("edition")(input).is_ok() {
+        return fail(
+            input,
+            "editions are not supported; only \"proto2\" and \"proto3\" syntax is",
+        );
+    }
+    locate(TAG, |input| {
+        let (input, _) = keyword("syntax")(input)?;
+        let (input, _) = cut(context("`=`", symbol('=')))(input)?;
+        let (input, _) = ws0(input)?;
+        let (rest, value) = cut(literal::string)(input)?;
+        let syntax = match &value[..] {
+            b"proto2" => Syntax::Proto2,
+            b"proto3" => Syntax::Proto3,
+            _ => {
+                return fail(
+                    input,
+                    format!(
+                        "unrecognized syntax identifier \"{}\"; only \"proto2\" and \"proto3\" are supported",
+                        String::from_utf8_lossy(&value),
+                    ),
+                )
+            }
+        };
+        let (rest, _) = cut(context("`;`", end_of_declaration(';', true)))(rest)?;
+        rest.extra.set_syntax(syntax);
+        Ok((rest, syntax))
+    })(input)
+}
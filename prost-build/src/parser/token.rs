@@ -0,0 +1,64 @@
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while, take_while1};
+use nom::character::complete::{char, satisfy};
+use nom::combinator::{not, opt, peek, recognize, value};
+use nom::multi::many0_count;
+use nom::sequence::{pair, preceded, terminated, tuple};
+use nom::AsChar;
+
+use super::{comment, literal, IResult, Span};
+
+/// Returns true if `c` may appear within an identifier.
+pub(super) fn is_ident_char(c: char) -> bool {
+    c.is_alphanum() || c == '_'
+}
+
+/// Returns true if `c` is whitespace between tokens.
+///
+/// As with `protoc`, this includes vertical tabs and form feeds, but not other Unicode whitespace.
+fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\r' | '\n' | '\x0b' | '\x0c')
+}
+
+/// Skips any whitespace and comments between tokens.
+///
+/// Comments within a declaration are not attached to anything, so they are discarded.
+pub(super) fn ws0(input: Span) -> IResult<()> {
+    value(
+        (),
+        many0_count(alt((
+            recognize(take_while1(is_whitespace)),
+            recognize(comment::parse),
+        ))),
+    )(input)
+}
+
+/// Parses a keyword, which must not be immediately followed by an identifier character.
+pub(super) fn keyword<'a>(word: &'static str) -> impl FnMut(Span<'a>) -> IResult<'a, Span<'a>> {
+    terminated(tag(word), not(peek(satisfy(is_ident_char))))
+}
+
+/// Parses a single-character symbol, preceded by optional whitespace.
+pub(super) fn symbol<'a>(c: char) -> impl FnMut(Span<'a>) -> IResult<'a, char> {
+    preceded(ws0, char(c))
+}
+
+/// Parses a single token: a string literal, identifier, number or symbol.
+pub(super) fn any(input: Span) -> IResult<Span> {
+    alt((
+        recognize(literal::single_string),
+        recognize(pair(
+            satisfy(|c| c.is_ascii_alphabetic() || c == '_'),
+            take_while(is_ident_char),
+        )),
+        recognize(tuple((
+            satisfy(|c| c.is_ascii_digit() || c == '.'),
+            take_while(|c| is_ident_char(c) || c == '.'),
+            opt(terminated(
+                satisfy(|c| c == '+' || c == '-'),
+                take_while(is_ident_char),
+            )),
+        ))),
+        recognize(satisfy(|c| !is_whitespace(c))),
+    ))(input)
+}
@@ -0,0 +1,578 @@
+use std::collections::{HashMap, HashSet};
+
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::{
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+};
+
+use super::field::to_json_name;
+use super::{start_of, Syntax, ValidationError};
+
+/// Checks a resolved file for errors which `protoc` would reject, returning every error found.
+pub(super) fn validate(file: &FileDescriptorProto) -> Vec<ValidationError> {
+    let syntax = match file.syntax() {
+        "proto3" => Syntax::Proto3,
+        _ => Syntax::Proto2,
+    };
+    let mut errors = Vec::new();
+    for (i, message) in file.message_type.iter().enumerate() {
+        let full_name = match file.package() {
+            "" => message.name().to_owned(),
+            package => format!("{}.{}", package, message.name()),
+        };
+        validate_message(
+            file,
+            syntax,
+            message,
+            &full_name,
+            &mut vec![4, i as i32],
+            &mut errors,
+        );
+    }
+    for (i, r#enum) in file.enum_type.iter().enumerate() {
+        validate_enum(file, syntax, r#enum, &[5, i as i32], &mut errors);
+    }
+    for (i, extension) in file.extension.iter().enumerate() {
+        validate_field(file, syntax, extension, &[7, i as i32], &mut errors);
+    }
+    errors
+}
+
+fn validate_message(
+    file: &FileDescriptorProto,
+    syntax: Syntax,
+    message: &DescriptorProto,
+    full_name: &str,
+    path: &mut Vec<i32>,
+    errors: &mut Vec<ValidationError>,
+) {
+    let mut names = HashSet::new();
+    for (i, field) in message.field.iter().enumerate() {
+        if !names.insert(field.name()) {
+            errors.push(ValidationError::at(
+                file,
+                &[&path[..], &[2, i as i32]].concat(),
+                format!(
+                    "\"{}\" is already defined in \"{}\"",
+                    field.name(),
+                    full_name
+                ),
+            ));
+        }
+    }
+    if syntax == Syntax::Proto3 {
+        errors.extend(check_json_names(file, message, path));
+    }
+    check_numbers(file, message, full_name, path, errors);
+    // The synthetic entry types of map fields set the option too, but have no location for it.
+    // `DescriptorProto.options` and `MessageOptions.map_entry`
+    let map_entry_path = [&path[..], &[7, 7]].concat();
+    if message.options.as_ref().map_or(false, |o| o.map_entry())
+        && start_of(file, &map_entry_path).is_some()
+    {
+        errors.push(ValidationError::at(
+            file,
+            &map_entry_path,
+            "map_entry should not be set explicitly; use map<KeyType, ValueType> instead"
+                .to_owned(),
+        ));
+    }
+    for (i, field) in message.field.iter().enumerate() {
+        let field_path = [&path[..], &[2, i as i32]].concat();
+        validate_field(file, syntax, field, &field_path, errors);
+    }
+    for (i, extension) in message.extension.iter().enumerate() {
+        let extension_path = [&path[..], &[6, i as i32]].concat();
+        validate_field(file, syntax, extension, &extension_path, errors);
+    }
+    for (i, nested) in message.nested_type.iter().enumerate() {
+        path.extend([3, i as i32]);
+        let nested_name = format!("{}.{}", full_name, nested.name());
+        validate_message(file, syntax, nested, &nested_name, path, errors);
+        path.truncate(path.len() - 2);
+    }
+    for (i, r#enum) in message.enum_type.iter().enumerate() {
+        validate_enum(
+            file,
+            syntax,
+            r#enum,
+            &[&path[..], &[4, i as i32]].concat(),
+            errors,
+        );
+    }
+}
+
+/// Checks a field or extension, which is located at `path`.
+fn validate_field(
+    file: &FileDescriptorProto,
+    syntax: Syntax,
+    field: &FieldDescriptorProto,
+    path: &[i32],
+    errors: &mut Vec<ValidationError>,
+) {
+    if syntax == Syntax::Proto3 && field.default_value.is_some() {
+        errors.push(ValidationError::at(
+            file,
+            path,
+            "explicit default values are not allowed in proto3".to_owned(),
+        ));
+    }
+    if field.label() == Label::Repeated && field.default_value.is_some() {
+        errors.push(ValidationError::at(
+            file,
+            path,
+            "repeated fields can't have default values".to_owned(),
+        ));
+    }
+    if !is_packable(field) && field.options.as_ref().map_or(false, |o| o.packed()) {
+        errors.push(ValidationError::at(
+            file,
+            path,
+            "[packed = true] can only be specified for repeated primitive fields".to_owned(),
+        ));
+    }
+}
+
+fn validate_enum(
+    file: &FileDescriptorProto,
+    syntax: Syntax,
+    r#enum: &EnumDescriptorProto,
+    path: &[i32],
+    errors: &mut Vec<ValidationError>,
+) {
+    // Proto3 enums are open, so their first value must be the zero default.
+    if syntax == Syntax::Proto3 && r#enum.value.first().map_or(false, |v| v.number() != 0) {
+        errors.push(ValidationError::at(
+            file,
+            // `EnumDescriptorProto.value` and `EnumValueDescriptorProto.number`
+            &[path, &[2, 0, 2]].concat(),
+            "the first enum value must be zero in proto3".to_owned(),
+        ));
+    }
+}
+
+/// Checks that the field numbers, extension ranges and reserved ranges of `message`, whose
+/// fully-qualified name without a leading `.` is `full_name`, don't overlap, as `protoc` does.
+///
+/// Ranges are stored with exclusive ends, but reported with inclusive ends as they are written.
+fn check_numbers(
+    file: &FileDescriptorProto,
+    message: &DescriptorProto,
+    full_name: &str,
+    path: &[i32],
+    errors: &mut Vec<ValidationError>,
+) {
+    let overlaps = |a: (i32, i32), b: (i32, i32)| a.0 < b.1 && b.0 < a.1;
+    let reserved: Vec<_> = message
+        .reserved_range
+        .iter()
+        .map(|range| (range.start(), range.end()))
+        .collect();
+    let extensions: Vec<_> = message
+        .extension_range
+        .iter()
+        .map(|range| (range.start(), range.end()))
+        .collect();
+
+    for (i, &range) in extensions.iter().enumerate() {
+        let at = [path, &[5, i as i32]].concat();
+        if let Some(&(start, end)) = extensions[..i]
+            .iter()
+            .find(|&&other| overlaps(range, other))
+        {
+            errors.push(ValidationError::at(
+                file,
+                &at,
+                format!(
+                    "extension range {} to {} overlaps with already-defined range {} to {}",
+                    range.0,
+                    range.1 - 1,
+                    start,
+                    end - 1
+                ),
+            ));
+        }
+        if let Some(&(start, end)) = reserved.iter().find(|&&other| overlaps(range, other)) {
+            errors.push(ValidationError::at(
+                file,
+                &at,
+                format!(
+                    "extension range {} to {} overlaps with reserved range {} to {}",
+                    range.0,
+                    range.1 - 1,
+                    start,
+                    end - 1
+                ),
+            ));
+        }
+    }
+    for (i, &range) in reserved.iter().enumerate() {
+        if let Some(&(start, end)) = reserved[..i].iter().find(|&&other| overlaps(range, other)) {
+            errors.push(ValidationError::at(
+                file,
+                &[path, &[9, i as i32]].concat(),
+                format!(
+                    "reserved range {} to {} overlaps with already-defined range {} to {}",
+                    range.0,
+                    range.1 - 1,
+                    start,
+                    end - 1
+                ),
+            ));
+        }
+    }
+    let mut numbers = HashMap::new();
+    for (i, field) in message.field.iter().enumerate() {
+        let number = field.number();
+        let first = *numbers.entry(number).or_insert(i);
+        if first != i {
+            errors.push(ValidationError::at(
+                file,
+                &[path, &[2, i as i32]].concat(),
+                format!(
+                    "field number {} has already been used in \"{}\" by field \"{}\"",
+                    number,
+                    full_name,
+                    message.field[first].name()
+                ),
+            ));
+        }
+        let contains = |&(start, end): &(i32, i32)| start <= number && number < end;
+        if reserved.iter().any(contains) {
+            errors.push(ValidationError::at(
+                file,
+                &[path, &[2, i as i32]].concat(),
+                format!("field \"{}\" uses reserved number {}", field.name(), number),
+            ));
+        }
+        if let Some(j) = extensions.iter().position(contains) {
+            let (start, end) = extensions[j];
+            errors.push(ValidationError::at(
+                file,
+                &[path, &[5, j as i32]].concat(),
+                format!(
+                    "extension range {} to {} includes field \"{}\" ({})",
+                    start,
+                    end - 1,
+                    field.name(),
+                    number
+                ),
+            ));
+        }
+    }
+}
+
+/// Returns whether `field` may use the packed encoding, which is only possible for repeated
+/// fields of scalar numeric types, including enums.
+fn is_packable(field: &FieldDescriptorProto) -> bool {
+    field.label() == Label::Repeated
+        && !matches!(
+            field.r#type(),
+            Type::String | Type::Bytes | Type::Message | Type::Group
+        )
+}
+
+/// Checks that no two fields of a proto3 message have the same JSON name, returning an error for
+/// the first conflict found.
+///
+/// As with `protoc`, both the default JSON names and the names used in practice, taking any
+/// `json_name` options into account, must be unique.
+fn check_json_names(
+    file: &FileDescriptorProto,
+    message: &DescriptorProto,
+    path: &[i32],
+) -> Option<ValidationError> {
+    for use_custom in [false, true] {
+        let mut seen: HashMap<String, (&str, bool)> = HashMap::new();
+        for (i, field) in message.field.iter().enumerate() {
+            let default = to_json_name(field.name());
+            let is_custom = use_custom && field.json_name.is_some() && field.json_name() != default;
+            let json_name = if is_custom {
+                field.json_name().to_owned()
+            } else {
+                default
+            };
+
+            // Fields with the same name are reported as duplicates instead.
+            if let Some(&(existing, existing_is_custom)) = seen
+                .get(&json_name)
+                .filter(|(existing, _)| *existing != field.name())
+            {
+                let kind = |custom| if custom { "custom" } else { "default" };
+                return Some(ValidationError::at(
+                    file,
+                    &[path, &[2, i as i32]].concat(),
+                    format!(
+                        "the {} JSON name of field \"{}\" (\"{}\") conflicts with the {} JSON name of field \"{}\"",
+                        kind(is_custom),
+                        field.name(),
+                        json_name,
+                        kind(existing_is_custom),
+                        existing,
+                    ),
+                ));
+            }
+            seen.insert(json_name, (field.name(), is_custom));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use crate::parser::parse;
+
+    fn validate(source: &str) -> Result<(), String> {
+        let files = HashMap::from([(PathBuf::from("test.proto"), source.to_owned())]);
+        parse(&files).map(|_| ()).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn rejects_conflicting_default_json_names() {
+        let error = validate(
+            "syntax = \"proto3\";\nmessage Foo {\n  int32 foo_bar = 1;\n  int32 fooBar = 2;\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:4:3: the default JSON name of field \"fooBar\" (\"fooBar\") conflicts with the default JSON name of field \"foo_bar\""
+        );
+    }
+
+    #[test]
+    fn rejects_explicit_map_entry_options() {
+        let error = validate(
+            "syntax = \"proto3\";\nmessage Entry {\n  option map_entry = true;\n  string key = 1;\n  string value = 2;\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:3:3: map_entry should not be set explicitly; use map<KeyType, ValueType> instead"
+        );
+
+        assert!(validate(
+            "syntax = \"proto3\";\nmessage Foo {\n  map<string, int32> counts = 1;\n}"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn rejects_defaults_on_repeated_fields() {
+        let error = validate(
+            "syntax = \"proto2\";\nmessage Foo {\n  repeated int32 a = 1 [default = 5];\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:3:3: repeated fields can't have default values"
+        );
+
+        let error = validate(
+            "syntax = \"proto2\";\nmessage M {\n  extensions 10 to 20;\n}\nextend M {\n  repeated int32 s = 10 [default = 1];\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:6:3: repeated fields can't have default values"
+        );
+    }
+
+    #[test]
+    fn rejects_overlapping_numbers() {
+        let error = validate(
+            "syntax = \"proto2\";\nmessage Foo {\n  reserved 10 to 20;\n  extensions 15 to 30;\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:4:14: extension range 15 to 30 overlaps with reserved range 10 to 20"
+        );
+
+        let error = validate(
+            "syntax = \"proto2\";\nmessage Foo {\n  reserved 2, 5 to max;\n  optional int32 a = 1;\n  optional int32 b = 7;\n}",
+        )
+        .unwrap_err();
+        assert_eq!(error, "test.proto:5:3: field \"b\" uses reserved number 7");
+
+        let error = validate(
+            "syntax = \"proto2\";\nmessage Foo {\n  extensions 100 to 199;\n  optional int32 a = 150;\n  reserved 1 to 3, 3;\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:5:20: reserved range 3 to 3 overlaps with already-defined range 1 to 3\n\
+             test.proto:3:14: extension range 100 to 199 includes field \"a\" (150)"
+        );
+
+        assert!(validate(
+            "syntax = \"proto2\";\nmessage Foo {\n  reserved 1 to 9;\n  extensions 10 to max;\n  optional int32 a = 9 [deprecated = true];\n}"
+        )
+        .is_err());
+        assert!(validate(
+            "syntax = \"proto2\";\nmessage Foo {\n  reserved 1 to 9;\n  extensions 10 to max;\n}"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn rejects_duplicate_field_numbers() {
+        let error = validate(
+            "syntax = \"proto3\";\npackage foo;\nmessage M {\n  int32 a = 1;\n  int32 b = 1;\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:5:3: field number 1 has already been used in \"foo.M\" by field \"a\""
+        );
+
+        let error = validate(
+            "syntax = \"proto3\";\nmessage M {\n  message N {\n    int32 a = 2;\n    oneof o { int32 b = 2; }\n  }\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:5:15: field number 2 has already been used in \"M.N\" by field \"a\""
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_custom_json_names() {
+        let error = validate(
+            "syntax = \"proto3\";\nmessage Foo {\n  int32 a = 1 [json_name = \"x\"];\n  int32 b = 2 [json_name = \"x\"];\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:4:3: the custom JSON name of field \"b\" (\"x\") conflicts with the custom JSON name of field \"a\""
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_field_names() {
+        let error = validate(
+            "syntax = \"proto2\";\npackage foo;\nmessage M {\n  optional int32 x = 1;\n  optional string x = 2;\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:5:3: \"x\" is already defined in \"foo.M\""
+        );
+
+        let error = validate(
+            "syntax = \"proto3\";\nmessage M {\n  message N {\n    int32 x = 1;\n    string x = 2;\n  }\n}",
+        )
+        .unwrap_err();
+        assert_eq!(error, "test.proto:5:5: \"x\" is already defined in \"M.N\"");
+    }
+
+    #[test]
+    fn allows_conflicting_json_names_in_proto2() {
+        validate("syntax = \"proto2\";\nmessage Foo {\n  optional int32 foo_bar = 1;\n  optional int32 fooBar = 2;\n}")
+            .unwrap();
+    }
+
+    #[test]
+    fn rejects_defaults_in_proto3() {
+        let error =
+            validate("syntax = \"proto3\";\nmessage Foo {\n  int32 a = 1 [default = 5];\n}")
+                .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:3:3: explicit default values are not allowed in proto3"
+        );
+
+        let files = HashMap::from([
+            (
+                PathBuf::from("base.proto"),
+                "syntax = \"proto2\";\nmessage M {\n  extensions 10 to 20;\n}".to_owned(),
+            ),
+            (
+                PathBuf::from("test.proto"),
+                "syntax = \"proto3\";\nimport \"base.proto\";\nextend M {\n  int32 i = 10 [default = 1];\n}"
+                    .to_owned(),
+            ),
+        ]);
+        assert_eq!(
+            parse(&files).unwrap_err().to_string(),
+            "test.proto:4:3: explicit default values are not allowed in proto3"
+        );
+    }
+
+    #[test]
+    fn allows_defaults_in_proto2() {
+        validate("syntax = \"proto2\";\nmessage Foo {\n  optional int32 a = 1 [default = 5];\n}")
+            .unwrap();
+    }
+
+    #[test]
+    fn requires_zero_first_enum_values_in_proto3() {
+        let error = validate("syntax = \"proto3\";\nenum E {\n  A = 1;\n  B = 0;\n}").unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:3:7: the first enum value must be zero in proto3"
+        );
+        let error =
+            validate("syntax = \"proto3\";\nmessage Foo {\n  enum E { A = 1; }\n}").unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:3:16: the first enum value must be zero in proto3"
+        );
+
+        validate("syntax = \"proto2\";\nenum E {\n  A = 1;\n  B = 0;\n}").unwrap();
+        validate("syntax = \"proto3\";\nenum E {\n  A = 0;\n  B = 1;\n}").unwrap();
+    }
+
+    #[test]
+    fn allows_packed_repeated_scalars() {
+        validate("syntax = \"proto2\";\nenum E { A = 0; }\nmessage Foo {\n  repeated int32 a = 1 [packed = true];\n  repeated E e = 2 [packed = true];\n  optional string s = 3 [packed = false];\n}")
+            .unwrap();
+    }
+
+    #[test]
+    fn rejects_packed_non_scalars() {
+        let error = validate(
+            "syntax = \"proto2\";\nmessage Foo {\n  repeated string a = 1 [packed = true];\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:3:3: [packed = true] can only be specified for repeated primitive fields"
+        );
+
+        let error = validate(
+            "syntax = \"proto2\";\nmessage Foo {\n  optional int32 a = 1 [packed = true];\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:3:3: [packed = true] can only be specified for repeated primitive fields"
+        );
+    }
+
+    #[test]
+    fn rejects_packed_non_scalar_extensions() {
+        let error = validate(
+            "syntax = \"proto2\";\nmessage M {\n  extensions 10 to 20;\n}\nextend M {\n  repeated string s = 10 [packed = true];\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:6:3: [packed = true] can only be specified for repeated primitive fields"
+        );
+
+        let error = validate(
+            "syntax = \"proto2\";\nmessage M {\n  extensions 10 to 20;\n  extend M {\n    optional int32 i = 10 [packed = true];\n  }\n}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "test.proto:5:5: [packed = true] can only be specified for repeated primitive fields"
+        );
+
+        validate("syntax = \"proto2\";\nmessage M {\n  extensions 10 to 20;\n}\nextend M {\n  repeated int32 i = 10 [packed = true];\n}")
+            .unwrap();
+    }
+}
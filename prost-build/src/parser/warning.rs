@@ -0,0 +1,25 @@
+use std::fmt;
+
+/// A problem with a `.proto` file which doesn't prevent it from being parsed, but may be a
+/// mistake.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    /// The name of the file containing the problem.
+    pub file: String,
+    /// The one-based line of the element with the problem.
+    pub line: usize,
+    /// The one-based column of the element with the problem, counted in characters.
+    pub column: usize,
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file, self.line, self.column, self.message
+        )
+    }
+}